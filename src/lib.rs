@@ -0,0 +1,482 @@
+//! (N)iels (S)tyle (S)heets, an indentation based language compiling to CSS.
+//!
+//! `compile_str` and `compile_file` cover the common case, the `lexer`, `parser`
+//! and `compiler` modules are there for going through the stages by hand.
+
+// `TokenType::EOF` and `lexer::lexer` are part of the original layout
+#![allow(clippy::upper_case_acronyms, clippy::module_inception)]
+
+extern crate colored;
+
+mod nss;
+
+pub use nss::{ error, source, lexer, parser, compiler };
+
+pub use error::{ Diagnostic, Diagnostics, Severity };
+pub use source::Source;
+pub use lexer::Lexer;
+pub use parser::{ Parser, Statement, StatementNode, Expression, ExpressionNode };
+pub use compiler::{ Compiler, OutputStyle };
+
+use std::fs;
+use std::thread;
+use std::path::{ Path, PathBuf };
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub max_errors: usize,       // reported by the parser before it gives up
+    pub load_paths: Vec<PathBuf>, // searched for `@use` after the using file's folder
+    pub inline_imports: bool,     // puts local CSS files of `@import` right into the output
+    pub style: OutputStyle,
+    pub source_map: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_errors: parser::MAX_ERRORS,
+            load_paths: Vec::new(),
+            inline_imports: false,
+            style: OutputStyle::default(),
+            source_map: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub css: String,
+    pub dependencies: Vec<String>, // files pulled in with `@use`, or inlined with `@import`
+    pub source_map: Option<String>, // meant to be written next to the CSS, as `<name>.css.map`
+}
+
+/// Compiles nss source that doesn't come from a file, diagnostics call it `<input>`.
+pub fn compile_str(content: &str, options: &Options) -> Result<Output, Diagnostics> {
+    compile_source("<input>", content, options)
+}
+
+pub fn compile_file<P: AsRef<Path>>(path: P, options: &Options) -> Result<Output, Diagnostics> {
+    let path = path.as_ref().display().to_string();
+
+    match fs::read_to_string(&path) {
+        Ok(content) => compile_source(&path, &content, options),
+        Err(error)  => Err(
+            Diagnostic::error(
+                format!("can't read `{}`: {}", path, error),
+                &source::FilePath(path.clone()),
+                lexer::Pos::none()
+            ).into()
+        ),
+    }
+}
+
+// mixins and functions may nest 100 deep before that's reported, which
+// takes more stack than threads get by default, in debug builds by far
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+fn compile_source(path: &str, content: &str, options: &Options) -> Result<Output, Diagnostics> {
+    thread::scope(|scope| {
+        let compiling = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || compile_on_thread(path, content, options))
+            .expect("failed to start the compiler thread");
+
+        match compiling.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    })
+}
+
+fn compile_on_thread(path: &str, content: &str, options: &Options) -> Result<Output, Diagnostics> {
+    let source = Source::from_content(path, content);
+
+    let mut tokens = Vec::new();
+
+    for token in Lexer::default(content.chars().collect(), &source) {
+        tokens.push(token?)
+    }
+
+    let ast = Parser::new(tokens, &source)
+        .with_max_errors(options.max_errors)
+        .parse()?;
+
+    let mut compiler = Compiler::new(&source)
+        .with_max_errors(options.max_errors)
+        .with_load_paths(options.load_paths.clone())
+        .with_inlined_imports(options.inline_imports)
+        .with_style(options.style);
+
+    let mut css = compiler.compile(ast)?;
+
+    let source_map = if options.source_map {
+        let file = Path::new(path).with_extension("css");
+        let file = file.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+        css.push_str(&format!("/*# sourceMappingURL={}.map */\n", file));
+
+        Some(compiler.source_map(&file))
+    } else {
+        None
+    };
+
+    Ok(
+        Output {
+            css,
+            dependencies: compiler.dependencies(),
+            source_map,
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(content: &str) -> String {
+        compile_str(content, &Options::default()).unwrap().css
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(compile(""), "");
+        assert_eq!(compile("\n\n  \n"), "");
+    }
+
+    #[test]
+    fn filter_functions() {
+        let css = compile("img\n  filter: grayscale(100%) invert(1) saturate(2)\n  color: grayscale(#f00)\n");
+
+        assert!(css.contains("filter: grayscale(100%) invert(1) saturate(2);"), "{}", css);
+        assert!(css.contains("color: #808080;"), "{}", css);
+    }
+
+    #[test]
+    fn css_math_functions() {
+        let css = compile("@gutter = 10px\n\na\n  width: calc(100% - @gutter * 2)\n  height: calc((100vh - 2rem) * 2)\n  margin: min(2px + 3px, 5vw)\n");
+
+        assert!(css.contains("width: calc(100% - 20px);"), "{}", css);
+        assert!(css.contains("height: calc((100vh - 2rem) * 2);"), "{}", css);
+        assert!(css.contains("margin: min(5px, 5vw);"), "{}", css);
+    }
+
+    #[test]
+    fn exponents() {
+        let css = compile("a\n  width: 1.5e3px\n  height: 2em\n  opacity: 1e-1\n");
+
+        assert!(css.contains("width: 1500px;"), "{}", css);
+        assert!(css.contains("height: 2em;"), "{}", css);
+        assert!(css.contains("opacity: 0.1;"), "{}", css);
+    }
+
+    #[test]
+    fn exponents_out_of_range() {
+        for content in ["a\n  width: 1e309px\n", "a\n  width: 1e999\n"] {
+            let error = compile_str(content, &Options::default()).unwrap_err().to_string();
+
+            assert!(error.contains("too large to be a number"), "{}", error);
+            assert!(error.contains("    2 |   width: 1e"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn slash_lists() {
+        let css = compile("@gutter = 10px\n\na\n  grid-area: 1 / 2 / 3\n  grid-column: header / main\n  font: 12px/1.5 serif\n  color: rgb(255 0 0 / 50%)\n  width: calc(100% / 4)\n  height: (10px / 2)\n  margin: @gutter / 2\n");
+
+        assert!(css.contains("grid-area: 1/2/3;"), "{}", css);
+        assert!(css.contains("grid-column: header/main;"), "{}", css);
+        assert!(css.contains("font: 12px/1.5 serif;"), "{}", css);
+        assert!(css.contains("color: rgb(255 0 0/50%);"), "{}", css);
+        assert!(css.contains("width: calc(25%);"), "{}", css);
+        assert!(css.contains("height: 5px;"), "{}", css);
+        assert!(css.contains("margin: 5px;"), "{}", css);
+    }
+
+    #[test]
+    fn negative_list_items() {
+        let css = compile("@gutter = 10px\n\na\n  margin: 0 -@gutter\n  padding: 0 -2px\n  width: 20px - @gutter\n  height: 20px -@gutter\n");
+
+        assert!(css.contains("margin: 0 -10px;"), "{}", css);
+        assert!(css.contains("padding: 0 -2px;"), "{}", css);
+        assert!(css.contains("width: 10px;"), "{}", css);
+        assert!(css.contains("height: 20px -10px;"), "{}", css);
+    }
+
+    #[test]
+    fn plain_diagnostics() {
+        fn compile(content: &str) -> Result<Output, Box<dyn std::error::Error>> {
+            Ok(compile_str(content, &Options::default())?)
+        }
+
+        let error = compile("a\n  color: @missing\n").unwrap_err().to_string();
+
+        assert!(error.contains("undefined variable `@missing`"), "{}", error);
+        assert!(!error.contains('\u{1b}'), "{:?}", error);
+    }
+
+    #[test]
+    fn large_integers() {
+        let css = compile("a\n  z-index: 3000000000\n  a: 100000000000\n  b: 123456789012\n  c: 1e20 + 1px\n  d: 2 ^ 1000\n  e: 0.1 + 0.2\n  f: -0.00000000001\n");
+
+        assert!(css.contains("z-index: 3000000000;"), "{}", css);
+        assert!(css.contains("a: 100000000000;"), "{}", css);
+        assert!(css.contains("b: 123456789012;"), "{}", css);
+        assert!(css.contains("c: 100000000000000000000px;"), "{}", css);
+        assert!(css.contains("d: 10715086071862673209484250490600018105614048117055336074437503883703510511249361224931983788156958581275946729175531468251871452856923140435984577574698574803934567774824230985421074605062371141877954182153046474983581941267398767559165543946077062914571196477686542167660429831652624386837205668069376;"), "{}", css);
+        assert!(css.contains("e: 0.3;"), "{}", css);
+        assert!(css.contains("f: 0;"), "{}", css);
+    }
+
+    #[test]
+    fn nested_rules() {
+        let css = compile("@base = 10px\n\n.nav\n  width: 96px + @base * 2 + 1in\n  &:hover\n    color: lighten(#336699, 20%)\n");
+
+        assert_eq!(css, ".nav {\n  width: 212px;\n}\n\n.nav:hover {\n  color: #6699cc;\n}\n\n");
+    }
+
+    #[test]
+    fn output_styles() {
+        let content = ".nav\n  margin: 0.5px 0\n  color: #336699\n  &:hover\n    color: white\n";
+
+        let css = |style| compile_str(content, &Options { style, ..Options::default() }).unwrap().css;
+
+        assert_eq!(css(OutputStyle::Compact), ".nav { margin: 0.5px 0; color: #336699; }\n.nav:hover { color: white; }\n");
+        assert_eq!(css(OutputStyle::Compressed), ".nav{margin:.5px 0;color:#369}.nav:hover{color:#fff}\n");
+    }
+
+    fn fixture(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+    }
+
+    #[test]
+    fn module_folders_are_searched_once() {
+        let options = Options { load_paths: vec![fixture("modules")], ..Options::default() };
+
+        let error = compile_file(fixture("modules/missing.nss"), &options).unwrap_err().to_string();
+
+        assert!(error.contains("can't find a module called `nowhere`"), "{}", error);
+        assert_eq!(error.matches("fixtures/modules`").count(), 1, "{}", error);
+    }
+
+    #[test]
+    fn used_modules_keep_the_error_limit() {
+        let options = Options { max_errors: 2, ..Options::default() };
+
+        let diagnostics = compile_file(fixture("modules/errors/main.nss"), &options).unwrap_err();
+
+        assert_eq!(diagnostics.error_count(), 2, "{}", diagnostics);
+        assert!(diagnostics.to_string().contains("stopped after 2 errors"), "{}", diagnostics);
+    }
+
+    #[test]
+    fn modules() {
+        let options = Options { load_paths: vec![fixture("modules/lib")], ..Options::default() };
+
+        let output = compile_file(fixture("modules/main.nss"), &options).unwrap();
+
+        // `tokens` is used by `main` and `theme/buttons`, but evaluated once
+        assert_eq!(output.css.matches(".tokens {").count(), 1, "{}", output.css);
+        assert!(output.css.starts_with(".tokens {\n  margin: 0;\n}"), "{}", output.css);
+
+        // through a namespace, `as b` and `as *` from a load path
+        assert!(output.css.contains(".a {\n  color: red;\n  width: 4px;\n  color: red;\n  padding: 4px;\n}"), "{}", output.css);
+
+        let dependencies = output.dependencies.iter()
+            .map(|path| path.replace('\\', "/"))
+            .collect::<Vec<String>>();
+
+        assert_eq!(dependencies.len(), 3, "{:?}", dependencies);
+        assert!(dependencies[0].ends_with("modules/_tokens.nss"), "{:?}", dependencies);
+        assert!(dependencies[1].ends_with("modules/theme/buttons.nss"), "{:?}", dependencies);
+        assert!(dependencies[2].ends_with("modules/lib/helpers.nss"), "{:?}", dependencies);
+    }
+
+    #[test]
+    fn module_errors() {
+        let error = |path| compile_file(fixture(path), &Options::default()).unwrap_err().to_string();
+
+        let ambiguous = error("modules/ambiguous/main.nss");
+
+        assert!(ambiguous.contains("`colors` could be"), "{}", ambiguous);
+        assert!(ambiguous.contains("help: remove or rename one of them"), "{}", ambiguous);
+
+        let cycle = error("modules/cycle/a.nss");
+
+        assert!(cycle.contains("modules use each other in a cycle") && cycle.contains("[use-cycle]"), "{}", cycle);
+        assert!(cycle.contains("cycle/a.nss` -> `"), "{}", cycle);
+        assert!(cycle.contains("cycle/_b.nss` is used here"), "{}", cycle);
+
+        let unknown = error("modules/unknown.nss");
+
+        assert!(unknown.contains("no module is used as `nope`"), "{}", unknown);
+    }
+
+    fn error(content: &str) -> String {
+        compile_str(content, &Options::default()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn mixin_arguments() {
+        let css = compile("@mixin box(@width, @color: red)\n  width: @width\n  color: @color\n\n.a\n  +box(10px)\n.b\n  +box(@color: blue, @width: 5px)\n.c\n  @include box(1px, green)\n");
+
+        assert_eq!(css, ".a {\n  width: 10px;\n  color: red;\n}\n\n.b {\n  width: 5px;\n  color: blue;\n}\n\n.c {\n  width: 1px;\n  color: green;\n}\n\n");
+
+        let error = error("@mixin box(@width)\n  width: @width\n\n.a\n  +box\n.b\n  +box(1px, 2px)\n.c\n  +box(@height: 1px)\n");
+
+        assert!(error.contains("`box` is missing an argument for `@width`"), "{}", error);
+        assert!(error.contains("`box` takes 1 arguments, found 2"), "{}", error);
+        assert!(error.contains("`box` has no parameter called `@height`"), "{}", error);
+        assert!(error.contains("    7 |   +box(1px, 2px)"), "{}", error);
+    }
+
+    #[test]
+    fn content_blocks() {
+        let css = compile("@size = outer\n\n@mixin tablet\n  @media (min-width: 768px)\n    @content\n\n@mixin wrap\n  .inner\n    @size = mixin\n    @content\n\n.a\n  +tablet\n    color: red\n    .b\n      color: blue\n  +wrap\n    width: @size\n");
+
+        assert!(css.contains("@media (min-width: 768px) {\n  .a {\n    color: red;\n  }\n\n  .a .b {\n    color: blue;\n  }\n}"), "{}", css);
+
+        // the block sees the variables of the include site, not the mixin's
+        assert!(css.contains(".a .inner {\n  width: outer;\n}"), "{}", css);
+    }
+
+    #[test]
+    fn functions() {
+        let css = compile("@base = 16\n\n@function rem(@px)\n  @base = 10\n  @return @px / @base * 1rem\n\n.a\n  width: rem(20)\n  height: @base\n");
+
+        assert!(css.contains("width: 2rem;"), "{}", css);
+        assert!(css.contains("height: 16;"), "{}", css);
+
+        let error = error("@function loop(@n)\n  @return loop(@n)\n\n@function none(@n)\n  @x = @n\n\n.a\n  height: loop(1)\n  top: none(1)\n");
+
+        assert!(error.contains("`loop` called more than 100 functions deep"), "{}", error);
+        assert!(error.contains("`none` finished without reaching a `@return`"), "{}", error);
+    }
+
+    #[test]
+    fn conditionals() {
+        let branches = |theme: &str| compile(&format!("@theme = {}\n\n.a\n  @if @theme == light\n    color: black\n  @else if @theme == dim\n    color: gray\n  @else if @theme == dark\n    color: white\n  @else\n    color: red\n", theme));
+
+        assert!(branches("light").contains("color: black;"));
+        assert!(branches("dim").contains("color: gray;"));
+        assert!(branches("dark").contains("color: white;"));
+        assert!(branches("other").contains("color: red;"));
+
+        let css = compile(".a\n  @if 1 > 2 or not true\n    top: 1px\n  @else if 2 >= 2 and 1 != 2\n    top: 2px\n  @if 1px == 1px + 0 and 1in == 96px\n    left: 0\n");
+
+        assert_eq!(css, ".a {\n  top: 2px;\n  left: 0;\n}\n\n");
+    }
+
+    #[test]
+    fn loops() {
+        let css = compile("@brands = (primary: red, secondary: blue)\n\n@each @name, @color in @brands\n  .text-#{@name}\n    color: @color\n\n@each @size in 1px 2px\n  .b-#{@size}\n    border: @size\n");
+
+        assert_eq!(css, ".text-primary {\n  color: red;\n}\n\n.text-secondary {\n  color: blue;\n}\n\n.b-1px {\n  border: 1px;\n}\n\n.b-2px {\n  border: 2px;\n}\n\n");
+
+        let css = compile("@for @i from 3 through 1\n  .mt-#{@i}\n    margin-top: @i * 4px\n\n@for @i from 3 to 1\n  .pt-#{@i}\n    padding-top: @i\n\n@for @i from 1 to 3\n  .ml-#{@i}\n    margin-left: @i\n");
+
+        let selectors = css.lines()
+            .filter(|line| line.ends_with('{'))
+            .collect::<Vec<&str>>();
+
+        assert_eq!(selectors, [".mt-3 {", ".mt-2 {", ".mt-1 {", ".pt-3 {", ".pt-2 {", ".ml-1 {", ".ml-2 {"]);
+        assert!(css.contains(".mt-1 {\n  margin-top: 4px;\n}"), "{}", css);
+    }
+
+    #[test]
+    fn interpolation() {
+        let css = compile("@name = logo\n@side = left\n@bp = 600px\n\n.icon-#{@name}\n  background: url(\"/img/#{@name}-#{1 + 1}.svg\")\n  border-#{@side}: 1px\n  content: \"#{@name}\"\n  &-#{@side}\n    margin: 0\n\n@media (min-width: #{@bp})\n  .a\n    color: red\n");
+
+        assert_eq!(css, ".icon-logo {\n  background: url(\"/img/logo-2.svg\");\n  border-left: 1px;\n  content: \"logo\";\n}\n\n.icon-logo-left {\n  margin: 0;\n}\n\n@media (min-width: 600px) {\n  .a {\n    color: red;\n  }\n}\n\n");
+    }
+
+    #[test]
+    fn variable_scopes() {
+        let css = compile("@color = red\n@size = 1px\n\n.a\n  @color = blue\n  color: @color\n\n.b\n  color: @color\n  @size = 2px !global\n  @size = 5px !default\n  @fresh = 3px !default\n  width: @fresh\n\n.c\n  width: @size\n\n@size = 9px !default\n.d\n  width: @size\n");
+
+        assert_eq!(css, ".a {\n  color: blue;\n}\n\n.b {\n  color: red;\n  width: 3px;\n}\n\n.c {\n  width: 2px;\n}\n\n.d {\n  width: 2px;\n}\n\n");
+
+        let error = error(".a\n  @local = 1px\n\n.b\n  width: @local\n");
+
+        assert!(error.contains("undefined variable `@local`"), "{}", error);
+    }
+
+    #[test]
+    fn css_imports() {
+        let path = fixture("imports/main.nss");
+
+        let output = compile_file(&path, &Options::default()).unwrap();
+
+        // hoisted above the rules, each one once
+        assert_eq!(output.css, "@import \"reset.css\";\n@import url(https://fonts.example.com/css?family=Inter&display=swap);\n@import \"print.css\" print;\n@import \"missing.css\";\n\n.a {\n  color: red;\n}\n\n");
+        assert!(output.dependencies.is_empty());
+
+        let options = Options { inline_imports: true, ..Options::default() };
+        let output  = compile_file(&path, &options).unwrap();
+
+        // local files go into the output, inside of their media query
+        assert_eq!(output.css, "@import url(https://fonts.example.com/css?family=Inter&display=swap);\n@import \"missing.css\";\n\nhtml { margin: 0; }\n\n@media print {\n  .p { color: red; }\n}\n\n.a {\n  color: red;\n}\n\n");
+        assert_eq!(output.dependencies.len(), 2);
+    }
+
+    fn parse_partial(content: &str) -> (Vec<Statement>, Diagnostics) {
+        let source = Source::from_content("<input>", content);
+        let tokens = Lexer::default(content.chars().collect(), &source)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        Parser::new(tokens, &source).parse_partial()
+    }
+
+    #[test]
+    fn recovers_at_line_boundaries() {
+        let (ast, diagnostics) = parse_partial(".a\n  width: (1px + 2px\n.b\n  color: )\n.c\n  color: red\n  top: rgb(1, 2\n");
+
+        // `.b` isn't swallowed by the unclosed `(` of `.a`
+        assert_eq!(ast.len(), 3, "{:?}", ast);
+        assert_eq!(diagnostics.error_count(), 3, "{}", diagnostics);
+
+        let errors = diagnostics.iter().map(|d| (d.message.as_str(), (d.pos.0).0, (d.pos.1).0)).collect::<Vec<_>>();
+
+        assert_eq!(errors, [("`(` is never closed", 2, 10), ("unexpected symbol `)`", 4, 10), ("`(` is never closed", 7, 11)]);
+
+        let css = compile("@brands = (\n  primary: red,\n  secondary: blue,\n)\n\n@mixin box(@a, @b)\n  width: @a\n  height: @b\n\n.a\n  +box(\n    1px,\n    2px\n  )\n");
+
+        assert!(css.contains("width: 1px;\n  height: 2px;"), "{}", css);
+    }
+
+    #[test]
+    fn caps_reported_errors() {
+        let content = "@a = )\n".repeat(5);
+
+        let options     = Options { max_errors: 3, ..Options::default() };
+        let diagnostics = compile_str(&content, &options).unwrap_err();
+
+        assert_eq!(diagnostics.error_count(), 3, "{}", diagnostics);
+        assert!(diagnostics.iter().last().is_some_and(|d| d.message == "stopped after 3 errors"), "{}", diagnostics);
+
+        let diagnostics = compile_str(&"@a = )\n".repeat(30), &Options::default()).unwrap_err();
+
+        assert_eq!(diagnostics.error_count(), parser::MAX_ERRORS);
+    }
+
+    #[test]
+    fn source_maps() {
+        let options = Options { source_map: true, ..Options::default() };
+        let output  = compile_file(fixture("sourcemap/main.nss"), &options).unwrap();
+
+        assert!(output.css.ends_with("}\n\n/*# sourceMappingURL=main.css.map */\n"), "{}", output.css);
+
+        // both files embedded as they are on disk, the entry has `\r\n` line endings
+        let map = output.source_map.unwrap();
+
+        assert_eq!(
+            map,
+            concat!(
+                "{\"version\":3,\"file\":\"main.css\",\"sources\":[\"main.nss\",\"_colors.nss\"],",
+                "\"sourcesContent\":[\"@use \\\"colors\\\"\\r\\n\\r\\n.a\\r\\n  color: colors.@primary\\r\\n\",",
+                "\"@primary = red\\n\\n.base\\n  margin: 0\\n\"],",
+                "\"names\":[],\"mappings\":\"ACEA;EACE;;;ADDF;EACE\"}\n"
+            )
+        );
+    }
+}
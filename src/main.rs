@@ -1,69 +1,78 @@
 extern crate colored;
+extern crate nss;
 
-mod nss;
+use nss::{ compile_file, Options, Output, OutputStyle };
 
-use nss::lexer::*;
-use nss::source::Source;
-use nss::parser::*;
-use nss::compiler::*;
-
-use std::fs::{ File, metadata, read_dir, write, remove_file };
-use std::{env, io::prelude::Read};
-use env::args;
-use std::path::Path;
+use std::fs::{ metadata, read_dir, write, remove_file };
+use std::env;
+use std::path::{ Path, PathBuf };
 
 use colored::Colorize;
 
-fn new_path(path: &String) -> String {
-    let mut paths = path.split("/").collect::<Vec<&str>>();
-    let file_name = paths.last().unwrap();
-    let new_file_name = file_name.replace(".nss", ".css");
-
-    paths.reverse();
-
-    let mut new_path = paths[1..].to_vec();
-    new_path.reverse();
-
-    format!("{}/{}", new_path.join("/"), new_file_name)
+fn new_path(path: &str) -> String {
+    Path::new(path).with_extension("css").display().to_string()
 }
 
-fn is_nss(path: &String) -> bool {
+fn is_nss(path: &str) -> bool {
     let paths = path.split("/").collect::<Vec<&str>>();
     let file_name = paths.last().unwrap();
 
     file_name.split(".").collect::<Vec<&str>>().last().unwrap() == &"nss"
 }
 
-fn compile_path(path: &String) {
-    let meta = metadata(&path).expect("Failed to get metadata.");
+// `_tokens.nss` and files given with `--partial` are only there to be used by other files
+fn is_partial(path: &str, partials: &[PathBuf]) -> bool {
+    let file_name = path.split("/").last().unwrap();
+
+    file_name.starts_with('_') || partials.iter().any(|partial| same_file(partial, Path::new(path)))
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn compile_path(path: &str, options: &Options, partials: &[PathBuf]) {
+    let meta = metadata(path).expect("Failed to get metadata.");
 
     if meta.is_dir() {
-        let dir = read_dir(&path).expect("Failed to read directory.");
+        let dir = read_dir(path).expect("Failed to read directory.");
 
         for path in dir {
             let path = path.unwrap().path().display().to_string();
-            compile_path(&path)
+
+            // a partial is compiled through the entry points using it
+            if is_nss(&path) && is_partial(&path, partials) {
+                continue
+            }
+
+            compile_path(&path, options, partials)
         }
-    } else {
-        if is_nss(path) {
-            match run(&path) {
-                Some(content) => {
-                    let status = format!("{} {}", "Compiled".green().bold(), path);
-                    write(new_path(&path), content).expect("Failed to write file.");
-                    println!("{}", status)
-                },
-    
-                None => ()
+    } else if is_nss(path) {
+        if let Some(output) = run(path, options) {
+            let status = format!("{} {}", "Compiled".green().bold(), path);
+            write(new_path(path), output.css).expect("Failed to write file.");
+
+            if let Some(source_map) = output.source_map {
+                write(format!("{}.map", new_path(path)), source_map).expect("Failed to write file.");
+            }
+
+            println!("{}", status);
+
+            for dependency in output.dependencies.iter() {
+                println!("{:>8} {}", "uses".cyan(), dependency)
             }
         }
     }
 }
 
-fn clean_path(path: &String) {
-    let meta = metadata(&path).expect("Failed to get metadata.");
+fn clean_path(path: &str) {
+    let meta = metadata(path).expect("Failed to get metadata.");
 
     if meta.is_dir() {
-        let dir = read_dir(&path).expect("Failed to read directory.");
+        let dir = read_dir(path).expect("Failed to read directory.");
 
         for path in dir {
             let path = &path.unwrap().path().display().to_string();
@@ -71,78 +80,141 @@ fn clean_path(path: &String) {
                 clean_path(path)
             }
         }
-    } else {
-        if is_nss(path) {
-            let path = new_path(&path);
+    } else if is_nss(path) {
+        let path = new_path(path);
+        let map  = format!("{}.map", path);
+
+        for path in [path, map] {
             if Path::new(&path).exists() {
                 let status = format!("{} {}", "Cleaned".magenta().bold(), path);
-                remove_file(path).expect("Failed to remove file.");
+                remove_file(&path).expect("Failed to remove file.");
                 println!("{}", status)
             }
         }
     }
 }
 
-fn run(path: &String) -> Option<String> {
-    let mut source = File::open(path.as_str()).unwrap();
-    let mut content = String::new();
-
-    source.read_to_string(&mut content).unwrap();
-
-    let source = Source::from(&path, content.lines().map(|x| x.into()).collect::<Vec<String>>());
-    let lexer  = Lexer::default(content.chars().collect(), &source);
+fn run(path: &str, options: &Options) -> Option<Output> {
+    match compile_file(path, options) {
+        Ok(output) => Some(output),
+        Err(diagnostics) => {
+            let errors = diagnostics.error_count();
 
-    let mut tokens = Vec::new();
+            print!("{}", diagnostics.colored());
+            println!("{} {} because of {} error{}", "Failed".red().bold(), path, errors, if errors == 1 { "" } else { "s" });
 
-    for token_r in lexer {
-        if let Ok(token) = token_r {
-            tokens.push(token)
-        } else {
-            return None
+            None
         }
     }
-
-    let mut parser = Parser::new(tokens, &source);
-
-    match parser.parse() {
-        Ok(ast) => {
-            let mut compiler = Compiler::new();
-            let result = compiler.compile(ast);
-
-            Some(result)
-        }
-
-        _ => None
-    }
 }
 
-const HELP: &'static str = r#"
+const HELP: &str = r#"
 (N)IELS (S)TYLE (S)HEETS
 ========================
 
 - nss <path>          # compile files in folder
 - nss <file>...       # compile one or more files
 - nss clean <path>... # remove compiled css files in one or more paths
+
+options:
+  -I, --load-path <dir> # also look for `@use`d files in <dir>
+  -p, --partial <file>  # never compile <file> on its own when compiling a folder,
+                        # like files starting with `_`
+  --inline-imports      # put local CSS files of `@import` right into the output
+  -s, --style <style>   # expanded (the default), compact or compressed
+  --source-map          # also write a `.css.map` next to every `.css`
 "#;
 
 fn main() {
-    let args: Vec<String> = args().collect();
+    let mut args: Vec<String> = Vec::new();
+    let mut options = Options::default();
+    let mut partials: Vec<PathBuf> = Vec::new();
+
+    let mut given = env::args();
+
+    while let Some(arg) = given.next() {
+        match arg.as_str() {
+            "-I" | "--load-path" => match given.next() {
+                Some(dir) => options.load_paths.push(dir.into()),
+                None => {
+                    println!("{} `{}` needs a folder", "Wrong".red().bold(), arg);
+                    return
+                }
+            },
 
-    if args.len() > 1 {
-        if args[1] == "clean" {
-            if args.len() > 2 {
-                for arg in args[2..].iter() {
-                    clean_path(arg)
+            "-p" | "--partial" => match given.next() {
+                Some(file) => partials.push(file.into()),
+                None => {
+                    println!("{} `{}` needs a file", "Wrong".red().bold(), arg);
+                    return
                 }
-            } else {
+            },
 
+            "--inline-imports" => options.inline_imports = true,
+            "--source-map"     => options.source_map = true,
+
+            "-s" | "--style" => match given.next().as_deref().and_then(OutputStyle::from_name) {
+                Some(style) => options.style = style,
+                None => {
+                    println!("{} `{}` needs one of expanded, compact or compressed", "Wrong".red().bold(), arg);
+                    return
+                }
+            },
+
+            _ => args.push(arg)
+        }
+    }
+
+    if args.len() > 1 {
+        if args[1] == "clean" {
+            for arg in args[2..].iter() {
+                clean_path(arg)
             }
         } else {
             for arg in args[1..].iter() {
-                compile_path(arg)
+                compile_path(arg, &options, &partials)
             }
         }
     } else {
         println!("{}", HELP)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{ copy, create_dir_all, remove_dir_all };
+
+    #[test]
+    fn partials() {
+        let shared = PathBuf::from("styles/shared.nss");
+
+        assert!(is_partial("styles/_tokens.nss", &[]));
+        assert!(!is_partial("styles/main.nss", &[]));
+        assert!(!is_partial("_styles/main.nss", &[]));
+        assert!(is_partial("styles/shared.nss", &[shared]));
+    }
+
+    #[test]
+    fn folders_skip_partials() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/partials");
+        let folder   = env::temp_dir().join(format!("nss-partials-{}", std::process::id()));
+
+        create_dir_all(&folder).unwrap();
+
+        for name in ["main.nss", "_tokens.nss", "shared.nss", "other.nss"] {
+            copy(fixtures.join(name), folder.join(name)).unwrap();
+        }
+
+        compile_path(&folder.display().to_string(), &Options::default(), &[folder.join("shared.nss")]);
+
+        let written = |name: &str| folder.join(name).exists();
+
+        let result = (written("main.css"), written("other.css"), written("_tokens.css"), written("shared.css"));
+
+        remove_dir_all(&folder).unwrap();
+
+        assert_eq!(result, (true, true, false, false));
+    }
+}
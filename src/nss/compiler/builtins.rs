@@ -0,0 +1,208 @@
+use super::*;
+
+use std::collections::HashMap;
+
+/// A function evaluated at compile time. `Ok(None)` leaves the call as plain CSS.
+pub type Builtin = fn(&str, &[Value]) -> Result<Option<Value>, String>;
+
+pub fn builtins() -> HashMap<&'static str, Builtin> {
+    let mut builtins: HashMap<&'static str, Builtin> = HashMap::new();
+
+    builtins.insert("lighten", lighten);
+    builtins.insert("darken", darken);
+    builtins.insert("saturate", saturate);
+    builtins.insert("desaturate", desaturate);
+    builtins.insert("mix", mix);
+    builtins.insert("rgb", rgba);
+    builtins.insert("rgba", rgba);
+    builtins.insert("complement", complement);
+    builtins.insert("invert", invert);
+    builtins.insert("grayscale", grayscale);
+
+    builtins
+}
+
+fn arity(name: &str, args: &[Value], min: usize, max: usize) -> Result<(), String> {
+    if args.len() < min || args.len() > max {
+        let expected = if min == max {
+            format!("{}", min)
+        } else {
+            format!("{} to {}", min, max)
+        };
+
+        Err(format!("`{}` takes {} arguments, found {}", name, expected, args.len()))
+    } else {
+        Ok(())
+    }
+}
+
+fn color_arg(name: &str, args: &[Value], i: usize) -> Result<Color, String> {
+    match args[i] {
+        Value::Color(ref color) => Ok(color.clone()),
+        ref v => Err(format!("argument {} of `{}` must be a color, found {}", i + 1, name, v.kind())),
+    }
+}
+
+// a percentage, `10%` and `10` mean the same
+fn amount_arg(name: &str, args: &[Value], i: usize) -> Result<f64, String> {
+    match args.get(i) {
+        Some(&Value::Number(n, None)) => Ok(n),
+        Some(&Value::Number(n, Some(ref unit))) if unit == "%" => Ok(n),
+        Some(v) => Err(format!("argument {} of `{}` must be a percentage, found {}", i + 1, name, v.kind())),
+        None => Ok(100.0),
+    }
+}
+
+// `filter: grayscale(100%)` and the like, css has filter functions by the same names
+fn is_filter(args: &[Value]) -> bool {
+    !matches!(args.first(), Some(Value::Color(_)))
+}
+
+fn adjust(name: &str, args: &[Value], saturation: f64, lightness: f64) -> Result<Option<Value>, String> {
+    arity(name, args, 2, 2)?;
+
+    let color  = color_arg(name, args, 0)?;
+    let amount = amount_arg(name, args, 1)?;
+
+    let (h, s, l) = color.to_hsl();
+
+    Ok(Some(Value::Color(
+        color.with_hsl(h, s + saturation * amount, l + lightness * amount)
+    )))
+}
+
+fn lighten(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    adjust(name, args, 0.0, 1.0)
+}
+
+fn darken(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    adjust(name, args, 0.0, -1.0)
+}
+
+fn saturate(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    if is_filter(args) {
+        return Ok(None)
+    }
+
+    adjust(name, args, 1.0, 0.0)
+}
+
+fn desaturate(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    adjust(name, args, -1.0, 0.0)
+}
+
+fn mix_colors(a: &Color, b: &Color, weight: f64) -> Color {
+    // same weighting as sass, taking the alpha of both colors into account
+    let p = weight / 100.0;
+    let w = p * 2.0 - 1.0;
+    let d = a.alpha - b.alpha;
+
+    let wa = if w * d == -1.0 { w } else { (w + d) / (1.0 + w * d) };
+    let wa = (wa + 1.0) / 2.0;
+    let wb = 1.0 - wa;
+
+    Color::new(
+        a.red * wa + b.red * wb,
+        a.green * wa + b.green * wb,
+        a.blue * wa + b.blue * wb,
+        a.alpha * p + b.alpha * (1.0 - p)
+    )
+}
+
+fn mix(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    arity(name, args, 2, 3)?;
+
+    let a = color_arg(name, args, 0)?;
+    let b = color_arg(name, args, 1)?;
+
+    let weight = if args.len() == 3 {
+        amount_arg(name, args, 2)?
+    } else {
+        50.0
+    };
+
+    Ok(Some(Value::Color(mix_colors(&a, &b, weight))))
+}
+
+fn rgba(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    if let Some(Value::Color(color)) = args.first() {
+        arity(name, args, 2, 2)?;
+
+        let alpha = match args[1] {
+            Value::Number(n, None) => n,
+            Value::Number(n, Some(ref unit)) if unit == "%" => n / 100.0,
+            ref v => return Err(format!("the alpha of `{}` must be a number, found {}", name, v.kind())),
+        };
+
+        return Ok(Some(Value::Color(
+            Color::new(color.red, color.green, color.blue, alpha)
+        )))
+    }
+
+    let mut channels = Vec::new();
+
+    for arg in args.iter() {
+        match *arg {
+            Value::Number(n, None) => channels.push(n),
+            Value::Number(n, Some(ref unit)) if unit == "%" => channels.push(n * 2.55),
+
+            // `rgb(var(--red), 0, 0)` and friends stay plain css
+            _ => return Ok(None)
+        }
+    }
+
+    arity(name, args, 3, 4)?;
+
+    // the alpha channel is 0-1, not 0-255
+    let alpha = match args.get(3) {
+        Some(&Value::Number(n, None)) => n,
+        Some(_) => channels[3] / 255.0,
+        None => 1.0,
+    };
+
+    Ok(Some(Value::Color(
+        Color::new(channels[0], channels[1], channels[2], alpha)
+    )))
+}
+
+fn complement(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    arity(name, args, 1, 1)?;
+
+    let color     = color_arg(name, args, 0)?;
+    let (h, s, l) = color.to_hsl();
+
+    Ok(Some(Value::Color(color.with_hsl(h + 180.0, s, l))))
+}
+
+fn invert(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    if is_filter(args) {
+        return Ok(None)
+    }
+
+    arity(name, args, 1, 2)?;
+
+    let color  = color_arg(name, args, 0)?;
+    let weight = amount_arg(name, args, 1)?;
+
+    let inverse = Color::new(
+        255.0 - color.red,
+        255.0 - color.green,
+        255.0 - color.blue,
+        color.alpha
+    );
+
+    Ok(Some(Value::Color(mix_colors(&inverse, &color, weight))))
+}
+
+fn grayscale(name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+    if is_filter(args) {
+        return Ok(None)
+    }
+
+    arity(name, args, 1, 1)?;
+
+    let color     = color_arg(name, args, 0)?;
+    let (h, _, l) = color.to_hsl();
+
+    Ok(Some(Value::Color(color.with_hsl(h, 0.0, l))))
+}
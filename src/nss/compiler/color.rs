@@ -0,0 +1,304 @@
+use std::fmt;
+
+use super::value::format_number;
+
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4), ("azure", 0xf0ffff), ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4), ("black", 0x000000), ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e), ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c), ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b), ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc), ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3), ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969), ("dimgrey", 0x696969), ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700), ("goldenrod", 0xdaa520), ("gray", 0x808080),
+    ("green", 0x008000), ("greenyellow", 0xadff2f), ("grey", 0x808080),
+    ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa), ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6), ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a), ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899), ("lightslategrey", 0x778899), ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa), ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371), ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5), ("navajowhite", 0xffdead), ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6), ("olive", 0x808000), ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093), ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f), ("pink", 0xffc0cb), ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513), ("salmon", 0xfa8072), ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee), ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f), ("steelblue", 0x4682b4), ("tan", 0xd2b48c),
+    ("teal", 0x008080), ("thistle", 0xd8bfd8), ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// An sRGB color, channels in 0-255 and alpha in 0-1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub alpha: f64,
+    // how the color was written, kept as long as the color isn't modified
+    pub repr: Option<String>,
+}
+
+impl Color {
+    pub fn new(red: f64, green: f64, blue: f64, alpha: f64) -> Self {
+        Self {
+            red: red.clamp(0.0, 255.0),
+            green: green.clamp(0.0, 255.0),
+            blue: blue.clamp(0.0, 255.0),
+            alpha: alpha.clamp(0.0, 1.0),
+            repr: None,
+        }
+    }
+
+    /// Parses the digits of `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None
+        }
+
+        let digits: Vec<f64> = match hex.len() {
+            3 | 4 => hex.chars()
+                .map(|c| u8::from_str_radix(&format!("{}{}", c, c), 16).unwrap() as f64)
+                .collect(),
+
+            6 | 8 => (0 .. hex.len() / 2)
+                .map(|i| u8::from_str_radix(&hex[i * 2 .. i * 2 + 2], 16).unwrap() as f64)
+                .collect(),
+
+            _ => return None
+        };
+
+        let alpha = digits.get(3).map(|a| a / 255.0).unwrap_or(1.0);
+
+        let mut color = Self::new(digits[0], digits[1], digits[2], alpha);
+        color.repr = Some(format!("#{}", hex));
+
+        Some(color)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let lowercase = name.to_lowercase();
+
+        let mut color = if lowercase == "transparent" {
+            Self::new(0.0, 0.0, 0.0, 0.0)
+        } else {
+            let &(_, rgb) = NAMED_COLORS.iter().find(|(n, _)| *n == lowercase)?;
+
+            Self::new(
+                ((rgb >> 16) & 0xff) as f64,
+                ((rgb >> 8) & 0xff) as f64,
+                (rgb & 0xff) as f64,
+                1.0
+            )
+        };
+
+        color.repr = Some(name.to_string());
+
+        Some(color)
+    }
+
+    /// Hue in degrees, saturation and lightness in percent.
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64, alpha: f64) -> Self {
+        let h = (hue % 360.0 + 360.0) % 360.0 / 360.0;
+        let s = saturation.clamp(0.0, 100.0) / 100.0;
+        let l = lightness.clamp(0.0, 100.0) / 100.0;
+
+        let q = if l < 0.5 { l * (1.0 + s) } else { l + s - l * s };
+        let p = 2.0 * l - q;
+
+        let channel = |t: f64| {
+            let t = if t < 0.0 { t + 1.0 } else if t > 1.0 { t - 1.0 } else { t };
+
+            let value = if t < 1.0 / 6.0 {
+                p + (q - p) * 6.0 * t
+            } else if t < 0.5 {
+                q
+            } else if t < 2.0 / 3.0 {
+                p + (q - p) * (2.0 / 3.0 - t) * 6.0
+            } else {
+                p
+            };
+
+            value * 255.0
+        };
+
+        Self::new(
+            channel(h + 1.0 / 3.0),
+            channel(h),
+            channel(h - 1.0 / 3.0),
+            alpha
+        )
+    }
+
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let r = self.red / 255.0;
+        let g = self.green / 255.0;
+        let b = self.blue / 255.0;
+
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+
+        let l = (max + min) / 2.0;
+
+        if max == min {
+            return (0.0, 0.0, l * 100.0)
+        }
+
+        let d = max - min;
+        let s = if l > 0.5 { d / (2.0 - max - min) } else { d / (max + min) };
+
+        let h = if max == r {
+            (g - b) / d + if g < b { 6.0 } else { 0.0 }
+        } else if max == g {
+            (b - r) / d + 2.0
+        } else {
+            (r - g) / d + 4.0
+        };
+
+        (h * 60.0, s * 100.0, l * 100.0)
+    }
+
+    pub fn with_hsl(&self, hue: f64, saturation: f64, lightness: f64) -> Self {
+        Self::from_hsl(hue, saturation, lightness, self.alpha)
+    }
+
+    pub fn to_hex(&self) -> String {
+        let mut hex = format!(
+            "#{:02x}{:02x}{:02x}",
+            self.red.round() as u8,
+            self.green.round() as u8,
+            self.blue.round() as u8,
+        );
+
+        if self.alpha < 1.0 {
+            hex.push_str(&format!("{:02x}", (self.alpha * 255.0).round() as u8))
+        }
+
+        hex
+    }
+}
+
+impl Color {
+    /// The shortest of its name, hex and rgba forms, for compressed output.
+    pub fn to_shortest(&self) -> String {
+        let mut candidates = Vec::new();
+
+        if let Some(ref repr) = self.repr {
+            candidates.push(repr.clone())
+        }
+
+        // a hex alpha is rounded to 255ths, it's only used when that's exact
+        if self.alpha >= 1.0 || (self.alpha * 255.0).fract() == 0.0 {
+            let hex = self.to_hex();
+
+            candidates.push(shorten_hex(&hex));
+            candidates.push(hex)
+        } else {
+            candidates.push(
+                format!(
+                    "rgba({},{},{},{})",
+                    self.red.round(),
+                    self.green.round(),
+                    self.blue.round(),
+                    format_number(self.alpha).trim_start_matches('0')
+                )
+            )
+        }
+
+        candidates.into_iter().min_by_key(|candidate| candidate.len()).unwrap()
+    }
+}
+
+// `#aabbcc` is `#abc`
+fn shorten_hex(hex: &str) -> String {
+    let digits = hex[1 ..].chars().collect::<Vec<char>>();
+
+    if digits.chunks(2).all(|pair| pair.len() == 2 && pair[0] == pair[1]) {
+        format!("#{}", digits.iter().step_by(2).collect::<String>())
+    } else {
+        hex.to_string()
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref repr) = self.repr {
+            return write!(f, "{}", repr)
+        }
+
+        if self.alpha < 1.0 {
+            write!(
+                f,
+                "rgba({}, {}, {}, {})",
+                self.red.round(),
+                self.green.round(),
+                self.blue.round(),
+                format_number(self.alpha)
+            )
+        } else {
+            write!(f, "{}", self.to_hex())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f64, f64, f64), b: (f64, f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6 && (a.2 - b.2).abs() < 1e-6
+    }
+
+    #[test]
+    fn to_hsl() {
+        assert!(close(Color::new(255.0, 0.0, 0.0, 1.0).to_hsl(), (0.0, 100.0, 50.0)));
+        assert!(close(Color::new(0.0, 128.0, 0.0, 1.0).to_hsl(), (120.0, 100.0, 128.0 / 5.1)));
+        assert!(close(Color::new(128.0, 128.0, 128.0, 1.0).to_hsl(), (0.0, 0.0, 128.0 / 2.55)));
+        assert!(close(Color::new(255.0, 0.0, 255.0, 1.0).to_hsl(), (300.0, 100.0, 50.0)));
+    }
+
+    #[test]
+    fn from_hsl() {
+        assert_eq!(Color::from_hsl(240.0, 100.0, 50.0, 1.0).to_hex(), "#0000ff");
+        assert_eq!(Color::from_hsl(-120.0, 100.0, 50.0, 1.0).to_hex(), "#0000ff");
+        assert_eq!(Color::from_hsl(0.0, 0.0, 100.0, 1.0).to_hex(), "#ffffff");
+        assert_eq!(Color::from_hsl(30.0, 100.0, 50.0, 1.0).to_hex(), "#ff8000");
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for hex in ["336699", "ff7f50", "00ced1", "8a2be2"] {
+            let color     = Color::from_hex(hex).unwrap();
+            let (h, s, l) = color.to_hsl();
+
+            assert_eq!(Color::from_hsl(h, s, l, 1.0).to_hex(), format!("#{}", hex));
+        }
+    }
+}
@@ -0,0 +1,1594 @@
+use super::super::error::{ Diagnostic, Diagnostics };
+use super::super::lexer::Lexer;
+use super::super::source::FilePath;
+use super::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::rc::Rc;
+
+// mixins including mixins or functions calling functions give up at some point
+const MAX_DEPTH: usize = 100;
+
+// the local scopes of a call site, and its module when the callee comes from another one
+type Caller = (Vec<Frame>, Option<Module>);
+
+// a block passed to a mixin, with the include site it belongs to
+type Content = (Option<Rc<Vec<Statement>>>, Caller);
+
+/// A mixin or function.
+#[derive(Debug)]
+pub struct Callable {
+    pub parameters: Vec<Parameter>,
+    pub body: Vec<Statement>,
+    pub module: String, // path of the module it's defined in
+}
+
+pub struct Compiler {
+    variables: Scopes,
+    mixins: HashMap<String, Rc<Callable>>,
+    functions: HashMap<String, Rc<Callable>>,
+    returned: Option<Value>, // by the function being called
+    in_function: bool,
+    content: Vec<Content>, // of the mixins being included
+    depth: usize,
+    builtins: HashMap<&'static str, Builtin>,
+    selectors: Vec<Selector>, // of the rule being compiled
+    media: Vec<Query>,        // of the media block being compiled
+    rule: Option<usize>,      // index of the rule taking declarations
+    rules: Vec<Rule>,
+    prelude: Prelude,
+    inline_imports: bool, // of local CSS files, instead of leaving them to the browser
+    style: OutputStyle,
+    mappings: Vec<Mapping>, // of the rendered output, for source maps
+    diagnostics: Diagnostics,
+    file: FilePath,
+    module: String,                            // path of the module whose code is running
+    namespaces: HashMap<String, Rc<Module>>,   // modules brought in with `@use`
+    modules: Rc<RefCell<Modules>>,
+    load_paths: Vec<PathBuf>,                  // searched for `@use` after the using file's folder
+    max_errors: usize,                         // reported by the parser of a used module
+}
+
+impl Compiler {
+    pub fn new(source: &Source) -> Self {
+        let module  = canonical(Path::new(&source.file.0));
+        let modules = Modules {
+            loaded: HashMap::new(),
+            loading: vec![
+                Loading {
+                    path: module.clone(),
+                    file: source.file.clone(),
+                    used_at: None,
+                }
+            ],
+            files: Vec::new(),
+            sources: vec![(source.file.clone(), source.content.clone())],
+        };
+
+        Self::for_module(source, module, Rc::new(RefCell::new(modules)), Vec::new())
+    }
+
+    pub fn with_load_paths(mut self, load_paths: Vec<PathBuf>) -> Self {
+        self.load_paths = load_paths;
+        self
+    }
+
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    pub fn with_inlined_imports(mut self, inline_imports: bool) -> Self {
+        self.inline_imports = inline_imports;
+        self
+    }
+
+    pub fn with_style(mut self, style: OutputStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn for_module(source: &Source, module: String, modules: Rc<RefCell<Modules>>, load_paths: Vec<PathBuf>) -> Self {
+        Self {
+            variables: Scopes::new(),
+            mixins: HashMap::new(),
+            functions: HashMap::new(),
+            returned: None,
+            in_function: false,
+            content: Vec::new(),
+            depth: 0,
+            builtins: builtins(),
+            selectors: Vec::new(),
+            media: Vec::new(),
+            rule: None,
+            rules: Vec::new(),
+            prelude: Prelude::default(),
+            inline_imports: false,
+            style: OutputStyle::default(),
+            mappings: Vec::new(),
+            diagnostics: Diagnostics::new(),
+            file: source.file.clone(),
+            module,
+            namespaces: HashMap::new(),
+            modules,
+            load_paths,
+            max_errors: MAX_ERRORS,
+        }
+    }
+
+    /// Compiles as much as possible, failing with everything that went wrong.
+    pub fn compile(&mut self, ast: Vec<Statement>) -> Result<String, Diagnostics> {
+        for statement in ast.iter() {
+            self.recover(statement)
+        }
+
+        if self.diagnostics.has_errors() {
+            Err(std::mem::take(&mut self.diagnostics))
+        } else {
+            let stylesheet = Stylesheet {
+                prelude: std::mem::take(&mut self.prelude),
+                rules: std::mem::take(&mut self.rules),
+            };
+
+            let (css, mappings) = render(&stylesheet, self.style);
+
+            self.mappings = mappings;
+
+            Ok(css)
+        }
+    }
+
+    /// A version 3 source map of the last compiled output, which is called `file`.
+    /// Sources are relative to the folder of the entry file, where the CSS goes.
+    pub fn source_map(&self, file: &str) -> String {
+        let modules = self.modules.borrow();
+        let entry   = &modules.loading[0].file;
+
+        sourcemap::generate(file, &entry.0, &modules.sources, &self.mappings)
+    }
+
+    /// The files `@use` pulled in, directly or through other modules.
+    pub fn dependencies(&self) -> Vec<String> {
+        self.modules.borrow().files.iter().map(|file| file.0.clone()).collect()
+    }
+
+    // compiling goes on with the next statement
+    fn recover(&mut self, statement: &Statement) {
+        if let Err(diagnostic) = self.compile_statement(statement) {
+            self.diagnostics.push(diagnostic)
+        }
+    }
+
+    pub fn compile_statement(&mut self, statement: &Statement) -> Result<(), Diagnostic> {
+        use self::StatementNode::*;
+
+        if self.in_function {
+            match statement.node {
+                Var(..) | Return(_) | If(..) | Each(..) | For(..) => (),
+
+                _ => return Err(
+                    Diagnostic::error(
+                        "functions can only assign variables and `@return`",
+                        &self.file,
+                        statement.pos.clone()
+                    )
+                )
+            }
+        }
+
+        match statement.node {
+            Definition(ref names, ref body) => {
+                let mut interpolated = Vec::new();
+
+                for name in names.iter() {
+                    interpolated.push(self.interpolate_selector(name)?)
+                }
+
+                let selectors = match selector::resolve(&self.selectors, &interpolated) {
+                    Ok(selectors) => selectors,
+                    Err(message) => return Err(
+                        Diagnostic::error(
+                            message,
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                };
+
+                self.rules.push(Rule::new(self.media.clone(), selectors.clone(), self.origin(&statement.pos)));
+
+                let parent_selectors = std::mem::replace(&mut self.selectors, selectors);
+                let parent_rule      = self.rule.replace(self.rules.len() - 1);
+
+                self.compile_rule_body(body);
+
+                self.selectors = parent_selectors;
+                self.rule      = parent_rule;
+            },
+
+            Media(ref queries, ref body) => {
+                let media = self.merge_media(queries, &statement.pos)?;
+
+                // a media block inside of a rule gets the declarations of that rule
+                let rule = if self.selectors.is_empty() {
+                    None
+                } else {
+                    self.rules.push(Rule::new(media.clone(), self.selectors.clone(), self.origin(&statement.pos)));
+
+                    Some(self.rules.len() - 1)
+                };
+
+                let outer_media = std::mem::replace(&mut self.media, media);
+                let outer_rule  = std::mem::replace(&mut self.rule, rule);
+
+                self.compile_rule_body(body);
+
+                self.media = outer_media;
+                self.rule  = outer_rule;
+            },
+
+            Style(ref name, ref expr) => {
+                let name   = self.interpolate(name)?.to_string();
+                let value  = self.evaluate(expr)?;
+                let origin = self.origin(&statement.pos);
+
+                match self.rule {
+                    Some(rule) => self.rules[rule].declarations.push(
+                        Declaration {
+                            name,
+                            value,
+                            origin,
+                        }
+                    ),
+
+                    None => return Err(
+                        Diagnostic::error(
+                            format!("`{}` has to be inside of a rule", name),
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                }
+            },
+
+            Var(ref name, ref expr, global, default) => {
+                let exists = if global {
+                    self.variables.get_global(name).is_some()
+                } else {
+                    self.variables.get(name).is_some()
+                };
+
+                if default && exists {
+                    return Ok(())
+                }
+
+                let value = self.evaluate(expr)?;
+
+                if global {
+                    self.variables.set_global(name, value)
+                } else {
+                    self.variables.set(name, value)
+                }
+            }
+
+            Mixin(ref name, ref parameters, ref body) => {
+                self.mixins.insert(
+                    name.clone(),
+                    Rc::new(
+                        Callable {
+                            parameters: parameters.clone(),
+                            body: body.clone(),
+                            module: self.module.clone(),
+                        }
+                    )
+                );
+            }
+
+            Function(ref name, ref parameters, ref body) => {
+                self.functions.insert(
+                    name.clone(),
+                    Rc::new(
+                        Callable {
+                            parameters: parameters.clone(),
+                            body: body.clone(),
+                            module: self.module.clone(),
+                        }
+                    )
+                );
+            }
+
+            Return(ref expr) => {
+                if !self.in_function {
+                    return Err(
+                        Diagnostic::error(
+                            "`@return` can only be used inside of a function",
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                }
+
+                let value = self.evaluate(expr)?;
+
+                self.returned = Some(value)
+            }
+
+            If(ref branches, ref fallback) => {
+                let mut body = fallback.as_ref();
+
+                for (condition, branch) in branches.iter() {
+                    if self.evaluate(condition)?.is_truthy() {
+                        body = Some(branch);
+                        break
+                    }
+                }
+
+                if let Some(body) = body {
+                    self.compile_scoped(body)?
+                }
+            }
+
+            Each(ref names, ref list, ref body) => {
+                let items = match self.evaluate(list)? {
+                    Value::List(items, _) => items,
+                    Value::Map(pairs) => pairs
+                        .into_iter()
+                        .map(|(key, value)| Value::List(vec![key, value], Separator::Space))
+                        .collect(),
+
+                    value => vec![value],
+                };
+
+                for item in items {
+                    // `@each @key, @value in …` takes every item apart
+                    let values = match item {
+                        Value::List(values, _) if names.len() > 1 => values,
+                        item => vec![item],
+                    };
+
+                    if values.len() < names.len() {
+                        return Err(
+                            Diagnostic::error(
+                                format!("can't take {} values apart into {} variables", values.len(), names.len()),
+                                &self.file,
+                                list.pos.clone()
+                            )
+                        )
+                    }
+
+                    self.variables.push();
+
+                    for (name, value) in names.iter().zip(values) {
+                        self.variables.declare(name, value)
+                    }
+
+                    let result = self.compile_block(body);
+
+                    self.variables.pop();
+
+                    result?;
+
+                    if self.returned.is_some() {
+                        break
+                    }
+                }
+            }
+
+            For(ref name, ref from, ref to, inclusive, ref body) => {
+                let (from, from_unit) = self.for_bound(from)?;
+                let (to, to_unit)     = self.for_bound(to)?;
+
+                let to = match (&from_unit, &to_unit) {
+                    (Some(a), Some(b)) => match unit::convert(to, b, a) {
+                        Some(to) => to.round(),
+                        None => return Err(
+                            Diagnostic::error(
+                                format!("`@for` can't count from `{}` to `{}`", a, b),
+                                &self.file,
+                                statement.pos.clone()
+                            )
+                        )
+                    },
+
+                    _ => to
+                };
+
+                let unit = from_unit.or(to_unit);
+
+                // counts down if `to` is smaller
+                let step = if from <= to { 1.0 } else { -1.0 };
+                let end  = if inclusive { to + step } else { to };
+
+                let mut i = from;
+
+                while i != end {
+                    self.variables.push();
+                    self.variables.declare(name, Value::Number(i, unit.clone()));
+
+                    let result = self.compile_block(body);
+
+                    self.variables.pop();
+
+                    result?;
+
+                    if self.returned.is_some() {
+                        break
+                    }
+
+                    i += step
+                }
+            }
+
+            Include(ref namespace, ref name, ref arguments, ref content) => {
+                let mixin = match *namespace {
+                    Some(ref namespace) => self.namespace(namespace, &statement.pos)?.mixins.get(name).cloned(),
+                    None => self.mixins.get(name).cloned(),
+                };
+
+                let name = match *namespace {
+                    Some(ref namespace) => format!("{}.{}", namespace, name),
+                    None => name.clone(),
+                };
+
+                let mixin = match mixin {
+                    Some(mixin) => mixin,
+                    None => return Err(
+                        Diagnostic::error(
+                            format!("there is no mixin called `{}`", name),
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                };
+
+                if self.depth >= MAX_DEPTH {
+                    return Err(
+                        Diagnostic::error(
+                            format!("`{}` is nested more than {} mixins deep, is it including itself?", name, MAX_DEPTH),
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                }
+
+                let caller = self.bind_arguments(&name, &mixin, arguments, &statement.pos)?;
+
+                self.depth += 1;
+                self.content.push((content.clone().map(Rc::new), caller));
+
+                let result = self.compile_block(&mixin.body);
+
+                let (_, caller) = self.content.pop().unwrap();
+
+                self.depth -= 1;
+                self.leave(caller);
+
+                result?;
+            }
+
+            Content => {
+                let content = match self.content.pop() {
+                    Some(content) => content,
+                    None => return Err(
+                        Diagnostic::error(
+                            "`@content` can only be used inside of a mixin",
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                };
+
+                let (body, (caller, outer)) = content;
+
+                // the block belongs to the include site, not to this mixin
+                let mixin        = self.variables.isolate();
+                let mixin_module = outer.map(|outer| self.enter(outer));
+
+                self.variables.restore(caller);
+
+                let result = match body {
+                    Some(ref body) => self.compile_scoped(body),
+                    None => Ok(())
+                };
+
+                let caller = self.variables.isolate();
+                let outer  = mixin_module.map(|module| self.enter(module));
+
+                self.variables.restore(mixin);
+                self.content.push((body, (caller, outer)));
+
+                result?;
+            }
+
+            Use(ref path, ref namespace) => {
+                if !self.is_top_level() {
+                    return Err(
+                        Diagnostic::error(
+                            "`@use` only works at the top level of a file",
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                }
+
+                let module = match self.load_module(path, &statement.pos) {
+                    Ok(module) => module,
+                    Err(errors) => {
+                        self.diagnostics.extend(errors);
+
+                        return Ok(())
+                    }
+                };
+
+                match namespace.as_deref() {
+                    // `as *` puts everything right into this file
+                    Some("*") => {
+                        for (name, value) in module.variables.iter() {
+                            self.variables.set_global(name, value.clone())
+                        }
+
+                        self.mixins.extend(module.mixins.iter().map(|(k, v)| (k.clone(), v.clone())));
+                        self.functions.extend(module.functions.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    },
+
+                    _ => {
+                        let name = namespace.clone().unwrap_or_else(|| module::namespace(path));
+
+                        if let Some(other) = self.namespaces.get(&name) {
+                            if other.path != module.path {
+                                return Err(
+                                    Diagnostic::error(
+                                        format!("`{}` and `{}` are both used as `{}`", other.file.0, module.file.0, name),
+                                        &self.file,
+                                        statement.pos.clone()
+                                    ).with_help(format!("rename one of them with `@use \"{}\" as …`", path))
+                                )
+                            }
+                        }
+
+                        self.namespaces.insert(name, module);
+                    },
+                }
+            }
+
+            Import(ref url, ref queries) => {
+                if !self.is_top_level() {
+                    return Err(
+                        Diagnostic::error(
+                            "`@import` only works at the top level of a file",
+                            &self.file,
+                            statement.pos.clone()
+                        )
+                    )
+                }
+
+                let media = self.merge_media(queries, &statement.pos)?;
+
+                match self.local_import(url) {
+                    Some(path) if self.inline_imports => {
+                        let file = FilePath(path.display().to_string());
+
+                        let css = match fs::read_to_string(&path) {
+                            Ok(css) => css,
+                            Err(error) => return Err(
+                                Diagnostic::error(
+                                    format!("can't read `{}`: {}", file.0, error),
+                                    &self.file,
+                                    statement.pos.clone()
+                                )
+                            )
+                        };
+
+                        let inlined = (css, media);
+
+                        // like modules, a file only ends up in the output once for the same media
+                        if !self.prelude.inlined.contains(&inlined) {
+                            self.prelude.inlined.push(inlined)
+                        }
+
+                        let mut modules = self.modules.borrow_mut();
+
+                        if !modules.files.contains(&file) {
+                            modules.files.push(file)
+                        }
+                    },
+
+                    _ => {
+                        let import = output::Import {
+                            url: url.clone(),
+                            media,
+                        };
+
+                        if !self.prelude.imports.contains(&import) {
+                            self.prelude.imports.push(import)
+                        }
+                    },
+                }
+            }
+
+            Expression(ref expr) => return Err(
+                Diagnostic::error(
+                    "a lonely value does nothing here, did you mean to assign it?",
+                    &self.file,
+                    expr.pos.clone()
+                )
+            ),
+        }
+
+        Ok(())
+    }
+
+    // evaluates the arguments where they're passed, then binds them to the parameters in a scope
+    // of their own that only sees globals of the callee's module, returning what `leave` needs
+    fn bind_arguments(&mut self, name: &str, callable: &Callable, arguments: &[Argument], pos: &Pos) -> Result<Caller, Diagnostic> {
+        let parameters = &callable.parameters;
+
+        let mut values: Vec<Option<Value>> = vec![None; parameters.len()];
+        let mut passed: Vec<Option<&Pos>>  = vec![None; parameters.len()];
+        let mut positional = 0;
+
+        for argument in arguments.iter() {
+            let index = match argument.name {
+                Some(ref argument_name) => match parameters.iter().position(|p| p.name == *argument_name) {
+                    Some(index) => index,
+                    None => return Err(
+                        Diagnostic::error(
+                            format!("`{}` has no parameter called `@{}`", name, argument_name),
+                            &self.file,
+                            argument.value.pos.clone()
+                        )
+                    )
+                },
+
+                None => {
+                    positional += 1;
+                    positional - 1
+                }
+            };
+
+            if index >= parameters.len() {
+                return Err(
+                    Diagnostic::error(
+                        format!("`{}` takes {} arguments, found {}", name, parameters.len(), arguments.len()),
+                        &self.file,
+                        pos.clone()
+                    )
+                )
+            }
+
+            if let Some(first) = passed[index] {
+                return Err(
+                    Diagnostic::error(
+                        format!("`@{}` is passed to `{}` twice", parameters[index].name, name),
+                        &self.file,
+                        argument.value.pos.clone()
+                    ).with_label(first.clone(), "passed here first")
+                )
+            }
+
+            passed[index] = Some(&argument.value.pos);
+            values[index] = Some(self.evaluate(&argument.value)?)
+        }
+
+        let caller = self.variables.isolate();
+        let outer  = self.module_of(callable).map(|module| self.enter(module));
+
+        self.variables.push();
+
+        // defaults may refer to earlier parameters, so they're bound one by one
+        for (parameter, value) in parameters.iter().zip(values) {
+            let value = match (value, &parameter.default) {
+                (Some(value), _) => Ok(value),
+                (None, Some(default)) => self.evaluate(default),
+                (None, None) => Err(
+                    Diagnostic::error(
+                        format!("`{}` is missing an argument for `@{}`", name, parameter.name),
+                        &self.file,
+                        pos.clone()
+                    )
+                )
+            };
+
+            match value {
+                Ok(value) => self.variables.declare(&parameter.name, value),
+                Err(diagnostic) => {
+                    self.leave((caller, outer));
+
+                    return Err(diagnostic)
+                }
+            }
+        }
+
+        Ok((caller, outer))
+    }
+
+    fn origin(&self, pos: &Pos) -> Origin {
+        Origin::new(&self.file.0, pos)
+    }
+
+    // outside of any rule, media block, mixin, function or control flow
+    fn is_top_level(&self) -> bool {
+        self.variables.is_global() && self.selectors.is_empty() && self.media.is_empty() && self.depth == 0
+    }
+
+    // the file an `@import` points to, if it's a local one that exists
+    fn local_import(&self, url: &str) -> Option<PathBuf> {
+        let url = url.trim_start_matches("url(").trim_end_matches(')').trim_matches('"');
+
+        if url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//") {
+            return None
+        }
+
+        let path = Path::new(&self.file.0).parent().unwrap_or(Path::new("")).join(url);
+
+        if path.is_file() { Some(path) } else { None }
+    }
+
+    // back to the call site after `bind_arguments`
+    fn leave(&mut self, caller: Caller) {
+        let (frames, outer) = caller;
+
+        if let Some(outer) = outer {
+            self.enter(outer);
+        }
+
+        self.variables.restore(frames)
+    }
+
+    // the module of a mixin or function, if it isn't the one whose code is running
+    fn module_of(&self, callable: &Callable) -> Option<Module> {
+        if callable.module == self.module {
+            return None
+        }
+
+        self.modules.borrow().loaded.get(&callable.module).map(|module| (**module).clone())
+    }
+
+    // makes the names of a module the visible ones, returning the ones that were
+    fn enter(&mut self, module: Module) -> Module {
+        use std::mem::replace;
+
+        Module {
+            path: replace(&mut self.module, module.path),
+            file: replace(&mut self.file, module.file),
+            variables: self.variables.replace_globals(module.variables),
+            mixins: replace(&mut self.mixins, module.mixins),
+            functions: replace(&mut self.functions, module.functions),
+            namespaces: replace(&mut self.namespaces, module.namespaces),
+        }
+    }
+
+    fn namespace(&self, name: &str, pos: &Pos) -> Result<Rc<Module>, Diagnostic> {
+        match self.namespaces.get(name) {
+            Some(module) => Ok(module.clone()),
+            None => Err(
+                Diagnostic::error(
+                    format!("no module is used as `{}`", name),
+                    &self.file,
+                    pos.clone()
+                )
+            )
+        }
+    }
+
+    // compiles a module the first time it's used, its CSS goes where it's first used
+    fn load_module(&mut self, path: &str, pos: &Pos) -> Result<Rc<Module>, Diagnostics> {
+        let resolved = self.resolve(path, pos)?;
+        let key      = canonical(&resolved);
+
+        if let Some(module) = self.modules.borrow().loaded.get(&key) {
+            return Ok(module.clone())
+        }
+
+        let cycle = self.modules.borrow().loading.iter().position(|loading| loading.path == key);
+
+        if let Some(start) = cycle {
+            return Err(self.cycle(start, pos).into())
+        }
+
+        let file    = resolved.display().to_string();
+        let content = match fs::read_to_string(&resolved) {
+            Ok(content) => content,
+            Err(error) => return Err(
+                Diagnostic::error(
+                    format!("can't read `{}`: {}", file, error),
+                    &self.file,
+                    pos.clone()
+                ).into()
+            )
+        };
+
+        let source = Source::from_content(&file, &content);
+
+        let mut tokens = Vec::new();
+
+        for token in Lexer::default(content.chars().collect(), &source) {
+            tokens.push(token?)
+        }
+
+        let ast = Parser::new(tokens, &source)
+            .with_max_errors(self.max_errors)
+            .parse()?;
+
+        self.modules.borrow_mut().sources.push((source.file.clone(), content));
+
+        self.modules.borrow_mut().loading.push(
+            Loading {
+                path: key.clone(),
+                file: source.file.clone(),
+                used_at: Some(pos.clone()),
+            }
+        );
+
+        let mut compiler = Compiler::for_module(&source, key.clone(), self.modules.clone(), self.load_paths.clone())
+            .with_max_errors(self.max_errors)
+            .with_inlined_imports(self.inline_imports);
+
+        for statement in ast.iter() {
+            compiler.recover(statement)
+        }
+
+        self.modules.borrow_mut().loading.pop();
+
+        if compiler.diagnostics.has_errors() {
+            return Err(compiler.diagnostics)
+        }
+
+        self.rules.extend(compiler.rules);
+        for import in compiler.prelude.imports {
+            if !self.prelude.imports.contains(&import) {
+                self.prelude.imports.push(import)
+            }
+        }
+
+        for inlined in compiler.prelude.inlined {
+            if !self.prelude.inlined.contains(&inlined) {
+                self.prelude.inlined.push(inlined)
+            }
+        }
+
+        let module = Rc::new(
+            Module {
+                path: key.clone(),
+                file: source.file,
+                variables: compiler.variables.globals().clone(),
+                mixins: compiler.mixins,
+                functions: compiler.functions,
+                namespaces: compiler.namespaces,
+            }
+        );
+
+        let mut modules = self.modules.borrow_mut();
+
+        modules.files.push(module.file.clone());
+        modules.loaded.insert(key, module.clone());
+
+        Ok(module)
+    }
+
+    // the file `@use` means, next to the using file or in one of the load paths
+    fn resolve(&self, path: &str, pos: &Pos) -> Result<PathBuf, Diagnostic> {
+        let base = Path::new(&self.file.0).parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let key = |folder: &Path| canonical(if folder.as_os_str().is_empty() { Path::new(".") } else { folder });
+
+        // the using file's folder may be a load path as well
+        let mut folders: Vec<PathBuf> = Vec::new();
+
+        for folder in std::iter::once(base).chain(self.load_paths.iter().cloned()) {
+            if !folders.iter().any(|seen| key(seen) == key(&folder)) {
+                folders.push(folder)
+            }
+        }
+
+        for folder in folders.iter() {
+            let found = candidates(&folder.join(path))
+                .into_iter()
+                .filter(|candidate| candidate.is_file())
+                .collect::<Vec<PathBuf>>();
+
+            match found.len() {
+                0 => continue,
+                1 => return Ok(found[0].clone()),
+
+                _ => return Err(
+                    Diagnostic::error(
+                        format!("`{}` could be `{}` or `{}`", path, found[0].display(), found[1].display()),
+                        &self.file,
+                        pos.clone()
+                    ).with_help("remove or rename one of them")
+                )
+            }
+        }
+
+        let looked = folders.iter()
+            .map(|folder| format!("`{}`", if folder.as_os_str().is_empty() { String::from(".") } else { folder.display().to_string() }))
+            .collect::<Vec<String>>();
+
+        Err(
+            Diagnostic::error(
+                format!("can't find a module called `{}`", path),
+                &self.file,
+                pos.clone()
+            ).with_note(format!("looked in {}", looked.join(", ")))
+        )
+    }
+
+    fn cycle(&self, start: usize, pos: &Pos) -> Diagnostic {
+        let modules = self.modules.borrow();
+        let chain   = &modules.loading[start ..];
+
+        let names = chain.iter()
+            .chain(std::iter::once(&chain[0]))
+            .map(|loading| format!("`{}`", loading.file.0))
+            .collect::<Vec<String>>();
+
+        let mut diagnostic = Diagnostic::error(
+            format!("modules use each other in a cycle: {}", names.join(" -> ")),
+            &self.file,
+            pos.clone()
+        ).with_code("use-cycle");
+
+        for (user, used) in chain.iter().zip(chain.iter().skip(1)) {
+            if let Some(ref used_at) = used.used_at {
+                diagnostic = diagnostic.with_label_in(
+                    &user.file,
+                    used_at.clone(),
+                    format!("`{}` is used here", used.file.0)
+                )
+            }
+        }
+
+        diagnostic
+    }
+
+    // a broken declaration or nested rule doesn't stop the rest of the rule
+    fn compile_rule_body(&mut self, body: &[Statement]) {
+        self.variables.push();
+
+        for statement in body.iter() {
+            self.recover(statement)
+        }
+
+        self.variables.pop()
+    }
+
+    // compiles a block with variables of its own
+    fn compile_scoped(&mut self, body: &[Statement]) -> Result<(), Diagnostic> {
+        self.variables.push();
+
+        let result = self.compile_block(body);
+
+        self.variables.pop();
+
+        result
+    }
+
+    // compiles statements until one of them returns from the function
+    fn compile_block(&mut self, body: &[Statement]) -> Result<(), Diagnostic> {
+        for statement in body.iter() {
+            self.compile_statement(statement)?;
+
+            if self.returned.is_some() {
+                break
+            }
+        }
+
+        Ok(())
+    }
+
+    fn for_bound(&mut self, expression: &Expression) -> Result<(f64, Option<String>), Diagnostic> {
+        match self.evaluate(expression)? {
+            Value::Number(n, unit) if n.fract() == 0.0 => Ok((n, unit)),
+
+            v => Err(
+                Diagnostic::error(
+                    format!("`@for` counts whole numbers, found {} `{}`", v.kind(), v),
+                    &self.file,
+                    expression.pos.clone()
+                )
+            )
+        }
+    }
+
+    fn interpolate(&mut self, name: &Interpolation) -> Result<Interpolation, Diagnostic> {
+        let mut text = String::new();
+
+        for segment in name.0.iter() {
+            match *segment {
+                Segment::Text(ref t) => text.push_str(t),
+                Segment::Expression(ref expression) => {
+                    text.push_str(&self.evaluate(expression)?.to_unquoted())
+                },
+            }
+        }
+
+        Ok(Interpolation::text(&text))
+    }
+
+    // the selector with every name interpolated
+    fn interpolate_selector(&mut self, selector: &Selector) -> Result<Selector, Diagnostic> {
+        use self::SimpleSelector::*;
+
+        let mut parts = Vec::new();
+
+        for part in selector.0.iter() {
+            let simples = match *part {
+                SelectorPart::Compound(ref simples) => simples,
+
+                ref combinator => {
+                    parts.push(combinator.clone());
+                    continue
+                }
+            };
+
+            let mut compound = Vec::new();
+
+            for simple in simples.iter() {
+                let simple = match *simple {
+                    Parent(Some(ref n)) => Parent(Some(self.interpolate(n)?)),
+                    Type(ref n)  => Type(self.interpolate(n)?),
+                    Class(ref n) => Class(self.interpolate(n)?),
+                    Id(ref n)    => Id(self.interpolate(n)?),
+
+                    PseudoClass(ref n, Some(PseudoArgument::Selectors(ref selectors))) => {
+                        let mut interpolated = Vec::new();
+
+                        for selector in selectors.iter() {
+                            interpolated.push(self.interpolate_selector(selector)?)
+                        }
+
+                        PseudoClass(n.clone(), Some(PseudoArgument::Selectors(interpolated)))
+                    },
+
+                    ref simple => simple.clone(),
+                };
+
+                compound.push(simple)
+            }
+
+            parts.push(SelectorPart::Compound(compound))
+        }
+
+        Ok(Selector(parts))
+    }
+
+    fn merge_media(&mut self, queries: &[MediaQuery], pos: &Pos) -> Result<Vec<Query>, Diagnostic> {
+        let mut evaluated = Vec::new();
+
+        for query in queries.iter() {
+            let mut features = Vec::new();
+
+            for feature in query.features.iter() {
+                features.push(self.compile_media_feature(feature)?)
+            }
+
+            evaluated.push(
+                Query {
+                    modifier: query.modifier.clone(),
+                    media_type: query.media_type.clone(),
+                    features,
+                }
+            )
+        }
+
+        if self.media.is_empty() {
+            return Ok(evaluated)
+        }
+
+        let mut merged = Vec::new();
+
+        for outer in self.media.iter() {
+            for inner in evaluated.iter() {
+                match outer.merge(inner) {
+                    Ok(Some(query)) => merged.push(query),
+                    Ok(None) => (),
+                    Err(message) => return Err(
+                        Diagnostic::error(
+                            message,
+                            &self.file,
+                            pos.clone()
+                        )
+                    )
+                }
+            }
+        }
+
+        Ok(merged)
+    }
+
+    fn compile_media_feature(&mut self, feature: &MediaFeature) -> Result<String, Diagnostic> {
+        let feature = match *feature {
+            MediaFeature::Boolean(ref name) => format!("({})", self.interpolate(name)?),
+            MediaFeature::Plain(ref name, ref value) => format!(
+                "({}: {})",
+                self.interpolate(name)?,
+                self.compile_expression(value)?
+            ),
+            MediaFeature::Interpolated(ref feature) => self.interpolate(feature)?.to_string(),
+            MediaFeature::Range(ref lower, ref name, ref upper) => {
+                let mut feature = String::from("(");
+
+                if let Some((ref value, ref comparison)) = *lower {
+                    feature.push_str(&format!("{} {} ", self.compile_expression(value)?, comparison))
+                }
+
+                feature.push_str(name);
+
+                if let Some((ref comparison, ref value)) = *upper {
+                    feature.push_str(&format!(" {} {}", comparison, self.compile_expression(value)?))
+                }
+
+                feature.push(')');
+                feature
+            },
+        };
+
+        Ok(feature)
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Result<String, Diagnostic> {
+        Ok(self.evaluate(expression)?.to_string())
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Result<Value, Diagnostic> {
+        use self::ExpressionNode::*;
+
+        let value = match expression.node {
+            Int(n)   => Value::Number(f64::from(n), None),
+            Float(n) => Value::Number(n, None),
+            Dimension(n, ref unit) => Value::Number(n, Some(unit.clone())),
+            Deref(ref n) => match self.variables.get(n) {
+                Some(value) => value.clone(),
+                None => {
+                    let diagnostic = Diagnostic::error(
+                        format!("undefined variable `@{}`", n),
+                        &self.file,
+                        expression.pos.clone()
+                    ).with_code("undefined-variable");
+
+                    return Err(
+                        match self.variables.suggest(n) {
+                            Some(similar) => diagnostic.with_help(format!("did you mean `@{}`?", similar)),
+                            None => diagnostic,
+                        }
+                    )
+                }
+            },
+            Member(ref namespace, ref member) => match member.node {
+                Deref(ref n) => match self.namespace(namespace, &expression.pos)?.variables.get(n) {
+                    Some(value) => value.clone(),
+                    None => return Err(
+                        Diagnostic::error(
+                            format!("undefined variable `{}.@{}`", namespace, n),
+                            &self.file,
+                            expression.pos.clone()
+                        ).with_code("undefined-variable")
+                    )
+                },
+
+                Identifier(ref n) => return Err(
+                    Diagnostic::error(
+                        format!("`{}.{}` is a function, it needs to be called", namespace, n),
+                        &self.file,
+                        expression.pos.clone()
+                    )
+                ),
+
+                _ => unreachable!(), // members are only ever variables or function names
+            },
+            Identifier(ref n) if n == "true"  => Value::Bool(true),
+            Identifier(ref n) if n == "false" => Value::Bool(false),
+            Identifier(ref n) => match self::super::Color::from_name(n) {
+                Some(color) => Value::Color(color),
+                None        => Value::Identifier(n.clone()),
+            },
+            Str(ref n) => Value::Str(n.clone()),
+            InterpolatedStr(ref n) => Value::Str(self.interpolate(n)?.to_string()),
+            Interpolation(ref n)   => Value::Identifier(self.interpolate(n)?.to_string()),
+            Call(ref n, ref args) => {
+                let name = match n.node {
+                    Identifier(ref name) => name.clone(),
+
+                    Member(ref namespace, ref member) if matches!(member.node, Identifier(_)) => {
+                        let function = match member.node {
+                            Identifier(ref function) => function,
+                            _ => unreachable!(),
+                        };
+
+                        let name = format!("{}.{}", namespace, function);
+
+                        return match self.namespace(namespace, &n.pos)?.functions.get(function).cloned() {
+                            Some(function) => self.call_function(&name, &function, args, &expression.pos),
+                            None => Err(
+                                Diagnostic::error(
+                                    format!("there is no function called `{}`", name),
+                                    &self.file,
+                                    n.pos.clone()
+                                )
+                            )
+                        }
+                    },
+
+                    _ => self.compile_expression(n)?,
+                };
+
+                if let Some(function) = self.functions.get(&name).cloned() {
+                    return self.call_function(&name, &function, args, &expression.pos)
+                }
+
+                // css does the math of `calc(100% - 10px)` where units don't mix
+                let math = ["calc", "min", "max", "clamp"].contains(&name.as_str());
+
+                let mut values = Vec::new();
+
+                for arg in args.iter() {
+                    if let Some(ref arg_name) = arg.name {
+                        return Err(
+                            Diagnostic::error(
+                                format!("`{}` doesn't take named arguments like `@{}`", name, arg_name),
+                                &self.file,
+                                arg.value.pos.clone()
+                            )
+                        )
+                    }
+
+                    if math {
+                        values.push(self.evaluate_math(&arg.value)?)
+                    } else {
+                        values.push(self.evaluate(&arg.value)?)
+                    }
+                }
+
+                let builtin = self.builtins.get(name.as_str()).cloned();
+
+                match builtin.map(|builtin| builtin(&name, &values)) {
+                    Some(Ok(Some(value))) => value,
+                    Some(Err(message)) => return Err(
+                        Diagnostic::error(
+                            message,
+                            &self.file,
+                            expression.pos.clone()
+                        )
+                    ),
+
+                    _ => Value::Call(name, values)
+                }
+            },
+            Color(ref hex) => Value::Color(
+                self::super::Color::from_hex(hex).unwrap()
+            ),
+            // `and` and `or` only evaluate the right side when they need it
+            Binary(ref left, Operator::And, ref right) => match self.evaluate(left)? {
+                left if left.is_truthy() => self.evaluate(right)?,
+                left => left,
+            },
+            Binary(ref left, Operator::Or, ref right) => match self.evaluate(left)? {
+                left if left.is_truthy() => left,
+                _ => self.evaluate(right)?,
+            },
+            Binary(ref left, ref op, ref right) => {
+                let left  = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+
+                match self.operation(left, op, right, &expression.pos)? {
+                    Value::Number(n, _) if !n.is_finite() => return Err(
+                        Diagnostic::error(
+                            format!("`{}` doesn't result in a finite number", op),
+                            &self.file,
+                            expression.pos.clone()
+                        )
+                    ),
+
+                    value => value
+                }
+            },
+            Neg(ref n) => match self.evaluate(n)? {
+                Value::Number(n, unit) => Value::Number(-n, unit),
+                v => return Err(
+                    Diagnostic::error(
+                        format!("can't negate {}", v.kind()),
+                        &self.file,
+                        expression.pos.clone()
+                    )
+                )
+            },
+            Not(ref n) => Value::Bool(!self.evaluate(n)?.is_truthy()),
+            Map(ref pairs) => {
+                let mut values = Vec::new();
+
+                for (key, value) in pairs.iter() {
+                    values.push((self.evaluate(key)?, self.evaluate(value)?))
+                }
+
+                Value::Map(values)
+            },
+            List(ref items, ref separator) => {
+                let mut values = Vec::new();
+
+                for item in items.iter() {
+                    values.push(self.evaluate(item)?)
+                }
+
+                Value::List(values, separator.clone())
+            },
+            Important(ref n) => Value::Important(
+                Box::new(self.evaluate(n)?)
+            ),
+            EOF => return Err(
+                Diagnostic::error(
+                    "expected a value, found end of file",
+                    &self.file,
+                    expression.pos.clone()
+                )
+            ),
+        };
+
+        Ok(value)
+    }
+
+    fn call_function(&mut self, name: &str, function: &Callable, arguments: &[Argument], pos: &Pos) -> Result<Value, Diagnostic> {
+        if self.depth >= MAX_DEPTH {
+            return Err(
+                Diagnostic::error(
+                    format!("`{}` called more than {} functions deep, does it ever stop recursing?", name, MAX_DEPTH),
+                    &self.file,
+                    pos.clone()
+                )
+            )
+        }
+
+        let caller = self.bind_arguments(name, function, arguments, pos)?;
+
+        let in_function = std::mem::replace(&mut self.in_function, true);
+
+        self.depth += 1;
+
+        let result = self.compile_block(&function.body);
+
+        self.depth -= 1;
+
+        self.in_function = in_function;
+        self.leave(caller);
+
+        result?;
+
+        match self.returned.take() {
+            Some(value) => Ok(value),
+            None => Err(
+                Diagnostic::error(
+                    format!("`{}` finished without reaching a `@return`", name),
+                    &self.file,
+                    pos.clone()
+                )
+            )
+        }
+    }
+
+    // arithmetic inside of `calc` and friends, what can't be worked out is left as text for the browser
+    fn evaluate_math(&mut self, expression: &Expression) -> Result<Value, Diagnostic> {
+        use self::Operator::*;
+
+        match expression.node {
+            ExpressionNode::Binary(ref left, ref op, ref right) if matches!(*op, Add | Sub | Mul | Div) => {
+                let left_value  = self.evaluate_math(left)?;
+                let right_value = self.evaluate_math(right)?;
+
+                Ok(self.combine_math((left, left_value), op, (right, right_value), &expression.pos))
+            },
+
+            // `calc(100% / 3)` parses like the slash of `font: 12px/1.5`
+            ExpressionNode::List(ref items, Separator::Slash) => {
+                let mut value = self.evaluate_math(&items[0])?;
+
+                for (previous, item) in items.iter().zip(items.iter().skip(1)) {
+                    let item_value = self.evaluate_math(item)?;
+
+                    value = self.combine_math((previous, value), &Div, (item, item_value), &expression.pos)
+                }
+
+                Ok(value)
+            },
+
+            _ => self.evaluate(expression),
+        }
+    }
+
+    fn combine_math(&mut self, left: (&Expression, Value), op: &Operator, right: (&Expression, Value), pos: &Pos) -> Value {
+        if let (Value::Number(..), Value::Number(..)) = (&left.1, &right.1) {
+            if let Ok(value) = self.operation(left.1.clone(), op, right.1.clone(), pos) {
+                return value
+            }
+        }
+
+        let (_, precedence) = Operator::from_str(op.as_str()).unwrap();
+
+        Value::Identifier(
+            format!(
+                "{} {} {}",
+                math_operand(left.0, left.1, precedence, false),
+                op,
+                math_operand(right.0, right.1, precedence, true)
+            )
+        )
+    }
+
+    fn operation(&mut self, left: Value, op: &Operator, right: Value, pos: &Pos) -> Result<Value, Diagnostic> {
+        use self::Operator::*;
+        use self::Value::*;
+
+        match *op {
+            Eq    => return Ok(Bool(left.equals(&right))),
+            NotEq => return Ok(Bool(!left.equals(&right))),
+            _     => (),
+        }
+
+        let value = match (left, right) {
+            (Number(a, a_unit), Number(b, b_unit)) if op.is_comparison() => self.compare(
+                (a, a_unit),
+                op,
+                (b, b_unit),
+                pos
+            )?,
+
+            (Number(a, a_unit), Number(b, b_unit)) => self.arithmetic(
+                (a, a_unit),
+                op,
+                (b, b_unit),
+                pos
+            )?,
+
+            (Str(a), Str(b)) if *op == Add => Str(format!("{}{}", a, b)),
+            (Str(a), Identifier(b)) if *op == Add => Str(format!("{}{}", a, b)),
+            (Str(a), b @ Number(..)) if *op == Add => Str(format!("{}{}", a, b)),
+
+            (a, b) => return Err(
+                Diagnostic::error(
+                    format!(
+                        "can't apply `{}` to {} and {}",
+                        op,
+                        a.kind(),
+                        b.kind()
+                    ),
+                    &self.file,
+                    pos.clone()
+                )
+            )
+        };
+
+        Ok(value)
+    }
+
+    fn compare(&mut self, left: (f64, Option<String>), op: &Operator, right: (f64, Option<String>), pos: &Pos) -> Result<Value, Diagnostic> {
+        use self::Operator::*;
+
+        let (a, a_unit) = left;
+        let (b, b_unit) = right;
+
+        // a unitless number compares with any unit
+        let b = match (a_unit, b_unit) {
+            (Some(a_unit), Some(b_unit)) => match unit::convert(b, &b_unit, &a_unit) {
+                Some(b) => b,
+                None => return Err(
+                    Diagnostic::error(
+                        format!("can't compare `{}` and `{}`, the units are incompatible", a_unit, b_unit),
+                        &self.file,
+                        pos.clone()
+                    )
+                )
+            },
+
+            _ => b
+        };
+
+        let result = match *op {
+            Lt   => a < b,
+            Gt   => a > b,
+            LtEq => a <= b,
+            GtEq => a >= b,
+            _    => unreachable!(),
+        };
+
+        Ok(Value::Bool(result))
+    }
+
+    fn arithmetic(&mut self, left: (f64, Option<String>), op: &Operator, right: (f64, Option<String>), pos: &Pos) -> Result<Value, Diagnostic> {
+        use self::Operator::*;
+
+        let (a, a_unit) = left;
+        let (b, b_unit) = right;
+
+        if *op == Div && b == 0.0 {
+            return Err(
+                Diagnostic::error(
+                    "division by zero",
+                    &self.file,
+                    pos.clone()
+                )
+            )
+        }
+
+        let value = match (a_unit, b_unit) {
+            (a_unit, None) => match *op {
+                Add => Value::Number(a + b, a_unit),
+                Sub => Value::Number(a - b, a_unit),
+                Mul => Value::Number(a * b, a_unit),
+                Div => Value::Number(a / b, a_unit),
+                Pow => {
+                    if let Some(unit) = a_unit {
+                        return Err(
+                            Diagnostic::error(
+                                format!("can't raise a `{}` value to a power", unit),
+                                &self.file,
+                                pos.clone()
+                            )
+                        )
+                    }
+
+                    Value::Number(a.powf(b), None)
+                },
+
+                _ => unreachable!(), // comparisons are handled by `compare`
+            },
+
+            (None, Some(b_unit)) => match *op {
+                Add => Value::Number(a + b, Some(b_unit)),
+                Sub => Value::Number(a - b, Some(b_unit)),
+                Mul => Value::Number(a * b, Some(b_unit)),
+
+                _ => return Err(
+                    Diagnostic::error(
+                        format!("can't apply `{}` to a unitless number and `{}`", op, b_unit),
+                        &self.file,
+                        pos.clone()
+                    )
+                )
+            },
+
+            (Some(a_unit), Some(b_unit)) => {
+                let b = match unit::convert(b, &b_unit, &a_unit) {
+                    Some(b) => b,
+                    None => return Err(
+                        Diagnostic::error(
+                            format!("incompatible units `{}` and `{}`", a_unit, b_unit),
+                            &self.file,
+                            pos.clone()
+                        )
+                    )
+                };
+
+                match *op {
+                    Add => Value::Number(a + b, Some(a_unit)),
+                    Sub => Value::Number(a - b, Some(a_unit)),
+                    Div => Value::Number(a / b, None),
+
+                    _ => return Err(
+                        Diagnostic::error(
+                            format!("can't apply `{}` to `{}` and `{}`, compound units aren't supported", op, a_unit, b_unit),
+                            &self.file,
+                            pos.clone()
+                        )
+                    )
+                }
+            },
+        };
+
+        Ok(value)
+    }
+}
+
+// `(100% - 10px) * 2` needs its parentheses back once it's text
+fn math_operand(operand: &Expression, value: Value, precedence: u8, right: bool) -> String {
+    if let (ExpressionNode::Binary(_, ref op, _), Value::Identifier(_)) = (&operand.node, &value) {
+        let (_, inner) = Operator::from_str(op.as_str()).unwrap();
+
+        if inner < precedence || right && inner == precedence {
+            return format!("({})", value)
+        }
+    }
+
+    value.to_string()
+}
@@ -0,0 +1,22 @@
+pub mod value;
+pub mod unit;
+pub mod color;
+pub mod builtins;
+pub mod selector;
+pub mod scope;
+pub mod module;
+pub mod output;
+pub mod sourcemap;
+pub mod compiler;
+
+use self::super::lexer::Pos;
+use self::super::parser::*;
+use self::super::source::Source;
+
+pub use self::value::*;
+pub use self::color::*;
+pub use self::builtins::*;
+pub use self::output::*;
+pub use self::scope::*;
+pub use self::module::*;
+pub use self::compiler::*;
@@ -0,0 +1,68 @@
+use super::*;
+
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+use std::rc::Rc;
+
+use super::super::source::FilePath;
+
+/// What a file loaded with `@use` leaves behind, once it has been compiled.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub path: String, // canonical, tells modules apart
+    pub file: FilePath,
+    pub variables: Frame,
+    pub mixins: HashMap<String, Rc<Callable>>,
+    pub functions: HashMap<String, Rc<Callable>>,
+    pub namespaces: HashMap<String, Rc<Module>>, // of its own `@use`s
+}
+
+/// A module that is being compiled, with the `@use` that led to it.
+#[derive(Debug, Clone)]
+pub struct Loading {
+    pub path: String,
+    pub file: FilePath,
+    pub used_at: Option<Pos>, // in the file of the module before it, nothing for the entry file
+}
+
+/// Every module of one compilation, each of them is only compiled once.
+#[derive(Debug, Default)]
+pub struct Modules {
+    pub loaded: HashMap<String, Rc<Module>>,
+    pub loading: Vec<Loading>,
+    pub files: Vec<FilePath>, // of the loaded modules, in the order they finished
+    pub sources: Vec<(FilePath, String)>, // every nss file compiled, with its contents
+}
+
+pub fn canonical(path: &Path) -> String {
+    match path.canonicalize() {
+        Ok(path) => path.display().to_string(),
+        Err(_)   => path.display().to_string(), // `<input>` doesn't exist
+    }
+}
+
+// `tokens` may be `tokens.nss` or the partial `_tokens.nss`
+pub fn candidates(path: &Path) -> Vec<PathBuf> {
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None       => return Vec::new(),
+    };
+
+    let name = if name.ends_with(".nss") { name } else { format!("{}.nss", name) };
+
+    let mut candidates = vec![path.with_file_name(&name)];
+
+    if !name.starts_with('_') {
+        candidates.push(path.with_file_name(format!("_{}", name)))
+    }
+
+    candidates
+}
+
+// `@use "theme/_tokens.nss"` is known as `tokens`
+pub fn namespace(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let name = name.strip_suffix(".nss").unwrap_or(name);
+
+    name.trim_start_matches('_').to_string()
+}
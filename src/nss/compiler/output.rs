@@ -0,0 +1,403 @@
+use super::*;
+
+use std::fmt;
+
+/// A media query after evaluation, with its features rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub modifier: Option<String>,
+    pub media_type: Option<String>,
+    pub features: Vec<String>,
+}
+
+impl Query {
+    /// Combines a query nested inside of this one, `None` if they can never both match.
+    pub fn merge(&self, inner: &Query) -> Result<Option<Query>, String> {
+        if self.modifier.as_deref() == Some("not") || inner.modifier.as_deref() == Some("not") {
+            return Err(String::from("media queries using `not` can't be nested"))
+        }
+
+        let media_type = match (&self.media_type, &inner.media_type) {
+            (Some(a), Some(b)) if !a.eq_ignore_ascii_case(b) => return Ok(None),
+            (Some(a), _) => Some(a.clone()),
+            (None, b)    => b.clone(),
+        };
+
+        let modifier = if media_type.is_some() {
+            self.modifier.clone().or_else(|| inner.modifier.clone())
+        } else {
+            None
+        };
+
+        let mut features = self.features.clone();
+        features.extend(inner.features.iter().cloned());
+
+        Ok(
+            Some(
+                Query {
+                    modifier,
+                    media_type,
+                    features,
+                }
+            )
+        )
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+
+        if let Some(ref modifier) = self.modifier {
+            parts.push(modifier.clone())
+        }
+
+        if let Some(ref media_type) = self.media_type {
+            parts.push(media_type.clone())
+        }
+
+        let mut query = parts.join(" ");
+
+        if !self.features.is_empty() {
+            if !query.is_empty() {
+                query.push_str(" and ")
+            }
+
+            query.push_str(&self.features.join(" and "))
+        }
+
+        write!(f, "{}", query)
+    }
+}
+
+/// Where something in the output was written, zero based like source maps count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Origin {
+    pub fn new(file: &str, pos: &Pos) -> Self {
+        Self {
+            file: file.to_string(),
+            line: (pos.0).0.saturating_sub(1),
+            column: (pos.1).0.saturating_sub(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub value: Value,
+    pub origin: Origin,
+}
+
+/// A flattened rule, nested rules and media queries are already resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub media: Vec<Query>,
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<Declaration>,
+    pub origin: Origin,
+}
+
+impl Rule {
+    pub fn new(media: Vec<Query>, selectors: Vec<Selector>, origin: Origin) -> Self {
+        Self {
+            media,
+            selectors,
+            declarations: Vec::new(),
+            origin,
+        }
+    }
+}
+
+/// A place in the output and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub line: usize,
+    pub column: usize,
+    pub origin: Origin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputStyle {
+    #[default]
+    Expanded,   // a declaration per line
+    Compact,    // a rule per line
+    Compressed, // as small as it gets
+}
+
+impl OutputStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        use self::OutputStyle::*;
+
+        match name {
+            "expanded"   => Some(Expanded),
+            "compact"    => Some(Compact),
+            "compressed" => Some(Compressed),
+            _            => None,
+        }
+    }
+}
+
+/// An `@import` that's left to the browser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub url: String, // `"a.css"` or `url(a.css)`
+    pub media: Vec<Query>,
+}
+
+/// What has to come before every rule, CSS ignores an `@import` after one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prelude {
+    pub imports: Vec<Import>,
+    pub inlined: Vec<(String, Vec<Query>)>, // CSS of imported files, with the media they're for
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stylesheet {
+    pub prelude: Prelude,
+    pub rules: Vec<Rule>,
+}
+
+fn make_line(value: &str) -> String {
+    let mut out = String::new();
+
+    for line in value.lines() {
+        if !line.is_empty() {
+            out.push_str("  ");
+            out.push_str(line);
+        }
+
+        out.push('\n')
+    }
+
+    out
+}
+
+// wraps CSS in a media block
+fn wrap_media(media: &[Query], css: &str, style: OutputStyle) -> String {
+    match style {
+        OutputStyle::Compressed => format!("@media {}{{{}}}", join(media, ","), css.trim()),
+
+        _ => {
+            let mut out = format!("@media {} {{\n", join(media, ", "));
+
+            out.push_str(&make_line(css.trim_end()));
+            out.push_str("}\n");
+
+            if style == OutputStyle::Expanded {
+                out.push('\n')
+            }
+
+            out
+        },
+    }
+}
+
+// writes the output, keeping track of where every rule and declaration ends up
+struct Renderer {
+    style: OutputStyle,
+    out: String,
+    line: usize,
+    column: usize, // in UTF-16 units, like browsers count
+    mappings: Vec<Mapping>,
+}
+
+impl Renderer {
+    fn write(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.line  += 1;
+                self.column = 0
+            } else {
+                self.column += c.len_utf16()
+            }
+        }
+
+        self.out.push_str(text)
+    }
+
+    fn mark(&mut self, origin: &Origin) {
+        self.mappings.push(
+            Mapping {
+                line: self.line,
+                column: self.column,
+                origin: origin.clone(),
+            }
+        )
+    }
+
+    fn rule(&mut self, rule: &Rule, indent: &str) {
+        use self::OutputStyle::*;
+
+        match self.style {
+            Expanded => {
+                self.write(indent);
+                self.mark(&rule.origin);
+                self.write(&join(&rule.selectors, ", "));
+                self.write(" {\n");
+
+                for declaration in rule.declarations.iter() {
+                    self.write(indent);
+                    self.write("  ");
+                    self.mark(&declaration.origin);
+                    self.write(&format!("{}: {};\n", declaration.name, declaration.value))
+                }
+
+                self.write(indent);
+                self.write("}\n")
+            },
+
+            Compact => {
+                self.write(indent);
+                self.mark(&rule.origin);
+                self.write(&join(&rule.selectors, ", "));
+                self.write(" {");
+
+                for declaration in rule.declarations.iter() {
+                    self.write(" ");
+                    self.mark(&declaration.origin);
+                    self.write(&format!("{}: {};", declaration.name, declaration.value))
+                }
+
+                self.write(" }\n")
+            },
+
+            // no `;` after the last declaration
+            Compressed => {
+                let selectors = rule.selectors.iter()
+                    .map(|selector| selector.to_compressed())
+                    .collect::<Vec<String>>();
+
+                self.mark(&rule.origin);
+                self.write(&selectors.join(","));
+                self.write("{");
+
+                for (i, declaration) in rule.declarations.iter().enumerate() {
+                    if i > 0 {
+                        self.write(";")
+                    }
+
+                    self.mark(&declaration.origin);
+                    self.write(&format!("{}:{}", declaration.name, declaration.value.to_compressed()))
+                }
+
+                self.write("}")
+            },
+        }
+    }
+
+    fn media(&mut self, media: &[Query], rules: &[&Rule]) {
+        use self::OutputStyle::*;
+
+        if self.style == Compressed {
+            self.write(&format!("@media {}{{", join(media, ",")));
+
+            for rule in rules.iter() {
+                self.rule(rule, "")
+            }
+
+            return self.write("}")
+        }
+
+        self.write(&format!("@media {} {{\n", join(media, ", ")));
+
+        for (i, rule) in rules.iter().enumerate() {
+            // a blank line between expanded rules
+            if i > 0 && self.style == Expanded {
+                self.write("\n")
+            }
+
+            self.rule(rule, "  ")
+        }
+
+        self.write("}\n");
+
+        if self.style == Expanded {
+            self.write("\n")
+        }
+    }
+}
+
+/// Renders the prelude, then the rules, wrapping rules that share a media query in one block.
+/// Also returns where each rule and declaration ended up.
+pub fn render(stylesheet: &Stylesheet, style: OutputStyle) -> (String, Vec<Mapping>) {
+    let compressed = style == OutputStyle::Compressed;
+
+    let mut renderer = Renderer {
+        style,
+        out: String::new(),
+        line: 0,
+        column: 0,
+        mappings: Vec::new(),
+    };
+
+    for import in stylesheet.prelude.imports.iter() {
+        renderer.write(&format!("@import {}", import.url));
+
+        if !import.media.is_empty() {
+            renderer.write(&format!(" {}", join(&import.media, if compressed { "," } else { ", " })))
+        }
+
+        renderer.write(if compressed { ";" } else { ";\n" })
+    }
+
+    if !stylesheet.prelude.imports.is_empty() && style == OutputStyle::Expanded {
+        renderer.write("\n")
+    }
+
+    for (css, media) in stylesheet.prelude.inlined.iter() {
+        if !media.is_empty() {
+            renderer.write(&wrap_media(media, css, style))
+        } else if compressed {
+            renderer.write(css.trim())
+        } else {
+            renderer.write(css.trim_end());
+            renderer.write(if style == OutputStyle::Expanded { "\n\n" } else { "\n" })
+        }
+    }
+
+    let rules = &stylesheet.rules;
+    let mut i = 0;
+
+    while i < rules.len() {
+        let media     = &rules[i].media;
+        let mut group = Vec::new();
+
+        while i < rules.len() && rules[i].media == *media {
+            if !rules[i].declarations.is_empty() {
+                group.push(&rules[i])
+            }
+
+            i += 1
+        }
+
+        if group.is_empty() {
+            continue
+        }
+
+        if !media.is_empty() {
+            renderer.media(media, &group);
+            continue
+        }
+
+        for rule in group {
+            renderer.rule(rule, "");
+
+            if style == OutputStyle::Expanded {
+                renderer.write("\n")
+            }
+        }
+    }
+
+    if compressed && !renderer.out.is_empty() {
+        renderer.write("\n")
+    }
+
+    (renderer.out, renderer.mappings)
+}
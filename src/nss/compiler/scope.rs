@@ -0,0 +1,123 @@
+use super::*;
+
+use std::collections::HashMap;
+
+pub type Frame = HashMap<String, Value>;
+
+/// Variables of the blocks being compiled, the global scope first and the innermost block last.
+#[derive(Debug, Clone)]
+pub struct Scopes {
+    frames: Vec<Frame>,
+}
+
+impl Scopes {
+    pub fn new() -> Self {
+        Self {
+            frames: vec![Frame::new()],
+        }
+    }
+
+    pub fn push(&mut self) {
+        self.frames.push(Frame::new())
+    }
+
+    pub fn pop(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.frames.iter().rev().find_map(|frame| frame.get(name))
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.frames[0].get(name)
+    }
+
+    /// Assigns to the closest local variable of that name, otherwise declares it in the innermost block.
+    /// Globals are only shadowed, never written from inside of a block.
+    pub fn set(&mut self, name: &str, value: Value) {
+        for frame in self.frames[1 ..].iter_mut().rev() {
+            if let Some(variable) = frame.get_mut(name) {
+                *variable = value;
+                return
+            }
+        }
+
+        self.declare(name, value)
+    }
+
+    /// Binds in the innermost block, shadowing everything further out.
+    pub fn declare(&mut self, name: &str, value: Value) {
+        self.frames.last_mut().unwrap().insert(name.to_string(), value);
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.frames[0].insert(name.to_string(), value);
+    }
+
+    pub fn globals(&self) -> &Frame {
+        &self.frames[0]
+    }
+
+    // swaps in the globals of another module
+    pub fn replace_globals(&mut self, globals: Frame) -> Frame {
+        std::mem::replace(&mut self.frames[0], globals)
+    }
+
+    // whether no block is being compiled
+    pub fn is_global(&self) -> bool {
+        self.frames.len() == 1
+    }
+
+    /// The visible name closest to a misspelled one, if any is close enough.
+    pub fn suggest(&self, name: &str) -> Option<&str> {
+        let limit = (name.chars().count() / 3).max(1);
+
+        self.frames.iter()
+            .flat_map(|frame| frame.keys())
+            .map(|candidate| (distance(name, candidate), candidate))
+            .filter(|&(distance, _)| distance <= limit)
+            .min()
+            .map(|(_, candidate)| candidate.as_str())
+    }
+
+    /// Takes away every local scope, leaving the globals. `restore` puts them back.
+    pub fn isolate(&mut self) -> Vec<Frame> {
+        self.frames.split_off(1)
+    }
+
+    pub fn restore(&mut self, locals: Vec<Frame>) {
+        self.frames.truncate(1);
+        self.frames.extend(locals)
+    }
+}
+
+impl Default for Scopes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// levenshtein distance
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+
+    let mut row = (0 ..= b.len()).collect::<Vec<usize>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut previous = row[0];
+
+        row[0] = i + 1;
+
+        for j in 0 .. b.len() {
+            let substitution = previous + if a == b[j] { 0 } else { 1 };
+
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
@@ -0,0 +1,160 @@
+use super::*;
+
+fn has_parent(selector: &Selector) -> bool {
+    selector.0.iter().any(|part| match *part {
+        SelectorPart::Compound(ref simples) => simples.iter().any(|simple| matches!(*simple, SimpleSelector::Parent(_))),
+        _ => false,
+    })
+}
+
+fn add_suffix(simple: &SimpleSelector, suffix: &Interpolation) -> Result<SimpleSelector, String> {
+    use self::SimpleSelector::*;
+
+    let name = |n: &Interpolation| Interpolation::text(&format!("{}{}", n, suffix));
+
+    let simple = match *simple {
+        Type(ref n)  => Type(name(n)),
+        Class(ref n) => Class(name(n)),
+        Id(ref n)    => Id(name(n)),
+
+        ref s => return Err(format!("can't add suffix `{}` to `{}`", suffix, s)),
+    };
+
+    Ok(simple)
+}
+
+// replaces every `&` in the child with the parent selector
+fn substitute(parent: &Selector, child: &Selector) -> Result<Selector, String> {
+    let mut parts = Vec::new();
+
+    for part in child.0.iter() {
+        let simples = match *part {
+            SelectorPart::Compound(ref simples) => simples,
+
+            ref combinator => {
+                parts.push(combinator.clone());
+                continue
+            }
+        };
+
+        let mut compound = Vec::new();
+
+        for simple in simples.iter() {
+            let suffix = match *simple {
+                SimpleSelector::Parent(ref suffix) => suffix,
+
+                ref simple => {
+                    compound.push(simple.clone());
+                    continue
+                }
+            };
+
+            if !compound.is_empty() {
+                return Err(String::from("`&` has to come first in a compound selector"))
+            }
+
+            let (last, init) = parent.0.split_last().unwrap();
+
+            parts.extend(init.iter().cloned());
+
+            if let SelectorPart::Compound(ref parent_simples) = *last {
+                compound.extend(parent_simples.iter().cloned());
+            }
+
+            if let Some(ref suffix) = *suffix {
+                let last = compound.pop().unwrap();
+
+                compound.push(add_suffix(&last, suffix)?)
+            }
+        }
+
+        parts.push(SelectorPart::Compound(compound))
+    }
+
+    Ok(Selector(parts))
+}
+
+/// Combines nested selectors with the selectors of the enclosing rule,
+/// every parent with every child.
+pub fn resolve(parents: &[Selector], children: &[Selector]) -> Result<Vec<Selector>, String> {
+    if parents.is_empty() {
+        if children.iter().any(has_parent) {
+            return Err(String::from("`&` can only be used inside of a rule"))
+        }
+
+        return Ok(children.to_vec())
+    }
+
+    let mut resolved = Vec::new();
+
+    for parent in parents.iter() {
+        for child in children.iter() {
+            let selector = if has_parent(child) {
+                substitute(parent, child)?
+            } else {
+                let mut parts = parent.0.clone();
+
+                if let Some(SelectorPart::Compound(_)) = child.0.first() {
+                    parts.push(SelectorPart::Combinator(Combinator::Descendant))
+                }
+
+                parts.extend(child.0.iter().cloned());
+
+                Selector(parts)
+            };
+
+            resolved.push(selector)
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::super::lexer::Lexer;
+
+    fn selectors(text: &str) -> Vec<Selector> {
+        let source = Source::from("<test>", vec![text.to_string()]);
+        let tokens = Lexer::default(text.chars().collect(), &source)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        Parser::new(tokens, &source).parse_selector_list().unwrap()
+    }
+
+    fn resolved(parents: &str, children: &str) -> Result<Vec<String>, String> {
+        let parents = if parents.is_empty() { Vec::new() } else { selectors(parents) };
+
+        Ok(
+            resolve(&parents, &selectors(children))?
+                .iter()
+                .map(|selector| selector.to_string())
+                .collect()
+        )
+    }
+
+    #[test]
+    fn descendants() {
+        assert_eq!(resolved("", "a, b").unwrap(), ["a", "b"]);
+        assert_eq!(resolved(".nav", "a").unwrap(), [".nav a"]);
+        assert_eq!(resolved(".a, .b", ".c, .d").unwrap(), [".a .c", ".a .d", ".b .c", ".b .d"]);
+        assert_eq!(resolved(".list", "> li").unwrap(), [".list > li"]);
+    }
+
+    #[test]
+    fn parent_selector() {
+        assert_eq!(resolved("a", "&:hover").unwrap(), ["a:hover"]);
+        assert_eq!(resolved(".nav a", "&.active").unwrap(), [".nav a.active"]);
+        assert_eq!(resolved(".button", "&-primary").unwrap(), [".button-primary"]);
+        assert_eq!(resolved(".a", ".b &").unwrap(), [".b .a"]);
+    }
+
+    #[test]
+    fn misplaced_parent_selector() {
+        assert!(resolved("", "&:hover").is_err());
+        assert!(resolved(".a", ".b&").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use super::*;
+
+use std::path::Path;
+
+use super::super::source::FilePath;
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// a signed number as base64 VLQ, the lowest bit is the sign
+fn vlq(value: i64, out: &mut String) {
+    let mut value = if value < 0 { (-value << 1) | 1 } else { value << 1 };
+
+    loop {
+        let mut digit = value & 31;
+
+        value >>= 5;
+
+        if value > 0 {
+            digit |= 32 // more digits follow
+        }
+
+        out.push(BASE64[digit as usize] as char);
+
+        if value == 0 {
+            break
+        }
+    }
+}
+
+// every field is relative to the one before it, the column only within a line
+fn encode(mappings: &[Mapping], sources: &[&str]) -> String {
+    let mut out = String::new();
+
+    let mut line          = 0;
+    let mut column        = 0;
+    let mut source        = 0;
+    let mut source_line   = 0;
+    let mut source_column = 0;
+
+    for (i, mapping) in mappings.iter().enumerate() {
+        if mapping.line != line {
+            while line < mapping.line {
+                out.push(';');
+                line += 1
+            }
+
+            column = 0
+        } else if i > 0 {
+            out.push(',')
+        }
+
+        let index = sources.iter().position(|file| *file == mapping.origin.file).unwrap_or(0) as i64;
+
+        vlq(mapping.column as i64 - column, &mut out);
+        vlq(index - source, &mut out);
+        vlq(mapping.origin.line as i64 - source_line, &mut out);
+        vlq(mapping.origin.column as i64 - source_column, &mut out);
+
+        column        = mapping.column as i64;
+        source        = index;
+        source_line   = mapping.origin.line as i64;
+        source_column = mapping.origin.column as i64;
+    }
+
+    out
+}
+
+fn json_string(string: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in string.chars() {
+        match c {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+// `path` as seen from the folder `from`, as it is if either doesn't exist
+fn relative(path: &str, from: &Path) -> String {
+    let from = if from.as_os_str().is_empty() { Path::new(".") } else { from };
+
+    let (path, from) = match (Path::new(path).canonicalize(), from.canonicalize()) {
+        (Ok(path), Ok(from)) => (path, from),
+        _ => return path.to_string(),
+    };
+
+    let path = path.components().collect::<Vec<_>>();
+    let from = from.components().collect::<Vec<_>>();
+
+    let common = path.iter().zip(from.iter()).take_while(|(a, b)| a == b).count();
+
+    let mut parts = vec![String::from(".."); from.len() - common];
+
+    parts.extend(path[common ..].iter().map(|part| part.as_os_str().to_string_lossy().to_string()));
+    parts.join("/")
+}
+
+/// Source map JSON for `file`, the CSS that was compiled from `entry`.
+pub fn generate(file: &str, entry: &str, sources: &[(FilePath, String)], mappings: &[Mapping]) -> String {
+    let folder = Path::new(entry).parent().unwrap_or(Path::new(""));
+
+    let files = sources.iter()
+        .map(|(file, _)| file.0.as_str())
+        .collect::<Vec<&str>>();
+
+    let paths = files.iter()
+        .map(|file| json_string(&relative(file, folder)))
+        .collect::<Vec<String>>();
+
+    let contents = sources.iter()
+        .map(|(_, content)| json_string(content))
+        .collect::<Vec<String>>();
+
+    format!(
+        "{{\"version\":3,\"file\":{},\"sources\":[{}],\"sourcesContent\":[{}],\"names\":[],\"mappings\":{}}}\n",
+        json_string(file),
+        paths.join(","),
+        contents.join(","),
+        json_string(&encode(mappings, &files))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(value: i64) -> String {
+        let mut out = String::new();
+
+        vlq(value, &mut out);
+        out
+    }
+
+    #[test]
+    fn vlq_values() {
+        assert_eq!(encoded(0), "A");
+        assert_eq!(encoded(1), "C");
+        assert_eq!(encoded(-1), "D");
+        assert_eq!(encoded(15), "e");
+        assert_eq!(encoded(16), "gB");
+        assert_eq!(encoded(-16), "hB");
+        assert_eq!(encoded(1000), "w+B");
+    }
+
+    #[test]
+    fn mappings_are_relative() {
+        let origin = |line, column| Origin { file: String::from("a.nss"), line, column };
+
+        let mappings = vec![
+            Mapping { line: 0, column: 0, origin: origin(0, 0) },
+            Mapping { line: 1, column: 2, origin: origin(1, 2) },
+            Mapping { line: 1, column: 8, origin: origin(1, 9) },
+            Mapping { line: 3, column: 2, origin: origin(2, 2) },
+        ];
+
+        assert_eq!(encode(&mappings, &["a.nss"]), "AAAA;EACE,MAAO;;EACP");
+    }
+}
@@ -0,0 +1,78 @@
+use std::f64::consts::PI;
+
+// (unit, group, size in the group's canonical unit)
+const UNITS: &[(&str, &str, f64)] = &[
+    ("px",   "length",     1.0),
+    ("pt",   "length",     4.0 / 3.0),
+    ("pc",   "length",     16.0),
+    ("in",   "length",     96.0),
+    ("cm",   "length",     96.0 / 2.54),
+    ("mm",   "length",     96.0 / 25.4),
+    ("q",    "length",     96.0 / 101.6),
+
+    ("ms",   "time",       1.0),
+    ("s",    "time",       1000.0),
+
+    ("deg",  "angle",      1.0),
+    ("grad", "angle",      0.9),
+    ("rad",  "angle",      180.0 / PI),
+    ("turn", "angle",      360.0),
+
+    ("hz",   "frequency",  1.0),
+    ("khz",  "frequency",  1000.0),
+
+    ("dpi",  "resolution", 1.0),
+    ("dpcm", "resolution", 2.54),
+    ("dppx", "resolution", 96.0),
+];
+
+fn lookup(unit: &str) -> Option<(&'static str, f64)> {
+    let unit = unit.to_lowercase();
+
+    UNITS.iter()
+        .find(|(name, _, _)| *name == unit)
+        .map(|&(_, group, size)| (group, size))
+}
+
+/// Converts `value` from one unit to another, if both measure the same thing.
+/// Relative units (em, rem, %, vw ...) only convert to themselves.
+pub fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+    if from.eq_ignore_ascii_case(to) {
+        return Some(value)
+    }
+
+    let (from_group, from_size) = lookup(from)?;
+    let (to_group, to_size)     = lookup(to)?;
+
+    if from_group == to_group {
+        Some(value * from_size / to_size)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    #[test]
+    fn converts_within_a_group() {
+        assert!(close(convert(1.0, "in", "px"), 96.0));
+        assert!(close(convert(2.54, "cm", "in"), 1.0));
+        assert!(close(convert(1.5, "s", "ms"), 1500.0));
+        assert!(close(convert(PI, "rad", "deg"), 180.0));
+        assert!(close(convert(1.0, "PX", "pt"), 0.75));
+    }
+
+    #[test]
+    fn relative_units_only_convert_to_themselves() {
+        assert!(close(convert(2.0, "em", "em"), 2.0));
+        assert_eq!(convert(1.0, "em", "px"), None);
+        assert_eq!(convert(1.0, "%", "px"), None);
+        assert_eq!(convert(1.0, "px", "ms"), None);
+    }
+}
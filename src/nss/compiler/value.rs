@@ -0,0 +1,187 @@
+use std::fmt;
+
+use super::color::Color;
+use super::unit;
+use super::Separator;
+
+// same precision as sass, enough to hide float noise like `0.30000000000000004`
+const PRECISION: usize = 10;
+
+pub fn format_number(n: f64) -> String {
+    // rounds the digits themselves, scaling big values by the precision would overflow
+    let formatted = format!("{:.*}", PRECISION, n);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+
+    if formatted == "-0" {
+        String::from("0") // no `-0`
+    } else {
+        formatted.to_string()
+    }
+}
+
+// `0.5` is `.5`
+fn compress_number(n: f64) -> String {
+    let formatted = format_number(n);
+
+    if let Some(fraction) = formatted.strip_prefix("0.") {
+        format!(".{}", fraction)
+    } else if let Some(fraction) = formatted.strip_prefix("-0.") {
+        format!("-.{}", fraction)
+    } else {
+        formatted
+    }
+}
+
+fn escape_string(string: &str) -> String {
+    let mut out = String::new();
+
+    for c in string.chars() {
+        match c {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\a "),
+            c    => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64, Option<String>),
+    Bool(bool),
+    Str(String),
+    Identifier(String),
+    Color(Color),
+    Call(String, Vec<Value>),
+    List(Vec<Value>, Separator),
+    Map(Vec<(Value, Value)>),
+    Important(Box<Value>),
+}
+
+impl Value {
+    pub fn kind(&self) -> &str {
+        use self::Value::*;
+
+        match *self {
+            Number(..)    => "number",
+            Bool(_)       => "boolean",
+            Str(_)        => "string",
+            Identifier(_) => "identifier",
+            Color(_)      => "color",
+            Call(..)      => "function call",
+            List(..)      => "list",
+            Map(_)        => "map",
+            Important(_)  => "important value",
+        }
+    }
+
+    /// How the value reads inside of a name, strings lose their quotes.
+    pub fn to_unquoted(&self) -> String {
+        match *self {
+            Value::Str(ref s) => s.clone(),
+            ref v => v.to_string(),
+        }
+    }
+
+    /// The shortest way to write the value, for compressed output.
+    pub fn to_compressed(&self) -> String {
+        use self::Value::*;
+
+        let compress = |values: &[Value], separator: &str| values.iter()
+            .map(|value| value.to_compressed())
+            .collect::<Vec<String>>()
+            .join(separator);
+
+        match *self {
+            Number(n, ref unit) => format!("{}{}", compress_number(n), unit.as_deref().unwrap_or("")),
+            Color(ref color)    => color.to_shortest(),
+            Call(ref n, ref args) => format!("{}({})", n, compress(args, ",")),
+            List(ref items, ref separator) => match *separator {
+                Separator::Comma => compress(items, ","),
+                ref separator    => compress(items, separator.as_str()),
+            },
+            Map(ref pairs) => {
+                let pairs = pairs.iter()
+                    .map(|(key, value)| format!("{}:{}", key.to_compressed(), value.to_compressed()))
+                    .collect::<Vec<String>>();
+
+                format!("({})", pairs.join(","))
+            },
+            Important(ref v) => format!("{}!important", v.to_compressed()),
+            ref v => v.to_string(),
+        }
+    }
+
+    /// Only `false` is falsy.
+    pub fn is_truthy(&self) -> bool {
+        *self != Value::Bool(false)
+    }
+
+    /// Equality as `==` sees it, `1in == 96px` and `red == #f00`.
+    pub fn equals(&self, other: &Value) -> bool {
+        use self::Value::*;
+
+        match (self, other) {
+            (Number(a, None), Number(b, None)) => a == b,
+            (Number(a, Some(a_unit)), Number(b, Some(b_unit))) => {
+                unit::convert(*b, b_unit, a_unit).is_some_and(|b| (a - b).abs() < 1e-10)
+            },
+            (Color(a), Color(b)) => {
+                a.red == b.red && a.green == b.green && a.blue == b.blue && a.alpha == b.alpha
+            },
+            (Str(a), Str(b)) | (Str(a), Identifier(b)) | (Identifier(a), Str(b)) | (Identifier(a), Identifier(b)) => a == b,
+            (List(a, a_sep), List(b, b_sep)) => {
+                a_sep == b_sep && a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(b))
+            },
+            (Map(a), Map(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|((a_key, a), (b_key, b))| a_key.equals(b_key) && a.equals(b))
+            },
+            (Important(a), Important(b)) => a.equals(b),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Value::*;
+
+        match *self {
+            Number(n, ref unit)  => write!(f, "{}{}", format_number(n), unit.as_deref().unwrap_or("")),
+            Bool(b)              => write!(f, "{}", b),
+            Str(ref s)           => write!(f, "\"{}\"", escape_string(s)),
+            Identifier(ref n)    => write!(f, "{}", n),
+            Color(ref color)     => write!(f, "{}", color),
+            Call(ref n, ref args) => {
+                write!(f, "{}(", n)?;
+
+                for (i, arg) in args.iter().enumerate() {
+                    write!(f, "{}", arg)?;
+
+                    if i != args.len() - 1 {
+                        write!(f, ", ")?
+                    }
+                }
+
+                write!(f, ")")
+            },
+            List(ref items, ref separator) => {
+                let items = items.iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>();
+
+                write!(f, "{}", items.join(separator.as_str()))
+            },
+            Map(ref pairs) => {
+                let pairs = pairs.iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<String>>();
+
+                write!(f, "({})", pairs.join(", "))
+            },
+            Important(ref v)     => write!(f, "{} !important", v),
+        }
+    }
+}
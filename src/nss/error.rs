@@ -1,37 +1,286 @@
 use colored::Colorize;
 use std::fmt;
+use std::ops::{ Deref, DerefMut };
+
+use super::lexer::Pos;
+use super::source::FilePath;
 
 pub enum Response<T: fmt::Display> {
     Wrong(T),
     Weird(T),
     Note(T),
+    Help(T),
 }
 
 use self::Response::*;
 
-#[macro_export]
-macro_rules! response {
-  ( $( $r:expr ),+ ) => {{
-    $(
-        print!("{}", $r);
-    )*
-    println!();
-  }};
-}
-
-impl<T: fmt::Display> fmt::Display for Response<T> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let (color, message_type, message) = match *self {
+impl<T: fmt::Display> Response<T> {
+    fn render(&self, color: bool) -> String {
+        let (name, message_type, message) = match *self {
             Wrong(ref m) => ("red", "wrong", m),
             Weird(ref m) => ("yellow", "weird", m),
             Note(ref m) => ("cyan", "note", m),
+            Help(ref m) => ("green", "help", m),
+        };
+
+        let message_type = format!("\n{}", message_type);
+
+        let message_type = if color {
+            message_type.color(name).bold().to_string()
+        } else {
+            message_type
+        };
+
+        format!("{}: {}", message_type, message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A secondary span, like where a conflicting thing was first seen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub file: Option<String>, // when it's in another file than the diagnostic
+    pub pos: Pos,
+    pub message: String,
+}
+
+/// Boxed, every parser and compiler function returns it as its error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic(Box<Details>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Details {
+    pub severity: Severity,
+    pub code: Option<&'static str>, // e.g. `undefined-variable`
+    pub message: String,
+    pub file: String,
+    pub pos: Pos,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new<M: Into<String>>(severity: Severity, message: M, file: &FilePath, pos: Pos) -> Self {
+        Self(
+            Box::new(
+                Details {
+                    severity,
+                    code: None,
+                    message: message.into(),
+                    file: file.0.clone(),
+                    pos,
+                    labels: Vec::new(),
+                    notes: Vec::new(),
+                    help: None,
+                }
+            )
+        )
+    }
+
+    pub fn error<M: Into<String>>(message: M, file: &FilePath, pos: Pos) -> Self {
+        Self::new(Severity::Error, message, file, pos)
+    }
+
+    pub fn warning<M: Into<String>>(message: M, file: &FilePath, pos: Pos) -> Self {
+        Self::new(Severity::Warning, message, file, pos)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label<M: Into<String>>(mut self, pos: Pos, message: M) -> Self {
+        self.labels.push(Label { file: None, pos, message: message.into() });
+        self
+    }
+
+    pub fn with_label_in<M: Into<String>>(mut self, file: &FilePath, pos: Pos, message: M) -> Self {
+        let file = if file.0 == self.file { None } else { Some(file.0.clone()) };
+
+        self.labels.push(Label { file, pos, message: message.into() });
+        self
+    }
+
+    pub fn with_note<M: Into<String>>(mut self, note: M) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help<M: Into<String>>(mut self, help: M) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Deref for Diagnostic {
+    type Target = Details;
+
+    fn deref(&self) -> &Details {
+        &self.0
+    }
+}
+
+impl DerefMut for Diagnostic {
+    fn deref_mut(&mut self) -> &mut Details {
+        &mut self.0
+    }
+}
+
+impl Diagnostic {
+    /// The diagnostic with terminal colors, `Display` is plain text.
+    pub fn colored(&self) -> Colored<'_, Self> {
+        Colored(self)
+    }
+
+    fn render(&self, f: &mut fmt::Formatter, color: bool) -> fmt::Result {
+        let message = match self.code {
+            Some(code) => format!("{} [{}]", self.message, code),
+            None       => self.message.clone(),
         };
 
-        let message_type = format!("\n{}", message_type).color(color).bold();
-        let message = format!("{}", message);
+        match self.severity {
+            Severity::Error   => write!(f, "{}", Wrong(message).render(color))?,
+            Severity::Warning => write!(f, "{}", Weird(message).render(color))?,
+            Severity::Note    => write!(f, "{}", Note(message).render(color))?,
+        }
+
+        write!(f, "{}", FilePath(self.file.clone()).render(color))?;
+
+        if (self.pos.0).0 > 0 {
+            write!(f, "{}", self.pos.render(color))?
+        }
+
+        for label in self.labels.iter() {
+            if let Some(ref file) = label.file {
+                write!(f, "{}", FilePath(file.clone()).render(color))?
+            }
+
+            write!(f, "{}{}", label.pos.render(color), Note(&label.message).render(color))?
+        }
 
-        let message = format!("{}: {}", message_type, message);
+        for note in self.notes.iter() {
+            write!(f, "{}", Note(note).render(color))?
+        }
 
-        write!(f, "{}", message)
+        if let Some(ref help) = self.help {
+            write!(f, "{}", Help(help).render(color))?
+        }
+
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.render(f, false)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Where every layer reports to, in the order things went wrong.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.items.push(diagnostic)
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.items.extend(other.items)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.items.iter().filter(|d| d.is_error()).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.items.iter().any(|d| d.is_error())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.items.iter()
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self {
+            items: vec![diagnostic],
+        }
+    }
+}
+
+impl Diagnostics {
+    /// Every diagnostic with terminal colors, `Display` is plain text.
+    pub fn colored(&self) -> Colored<'_, Self> {
+        Colored(self)
+    }
+
+    fn render(&self, f: &mut fmt::Formatter, color: bool) -> fmt::Result {
+        for diagnostic in self.items.iter() {
+            diagnostic.render(f, color)?
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.render(f, false)
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// Diagnostics as the command line prints them, see `colored`.
+pub struct Colored<'a, T>(&'a T);
+
+impl fmt::Display for Colored<'_, Diagnostic> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.render(f, true)
+    }
+}
+
+impl fmt::Display for Colored<'_, Diagnostics> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.render(f, true)
     }
 }
@@ -1,6 +1,6 @@
 use super::*;
 
-use super::super::error::Response::Wrong;
+use super::super::error::Diagnostic;
 
 use std::rc::Rc;
 
@@ -28,15 +28,25 @@ impl<'l> Lexer<'l> {
         lexer.matchers.push(Rc::new(CommentMatcher));
         lexer.matchers.push(Rc::new(EOLMatcher));
         lexer.matchers.push(Rc::new(StringLiteralMatcher));
+        lexer.matchers.push(Rc::new(HexColorMatcher));
 
         lexer.matchers.push(Rc::new(NumberLiteralMatcher));
         lexer.matchers.push(Rc::new(WhitespaceMatcher));
 
+        lexer.matchers.push(Rc::new(UrlMatcher));
         lexer.matchers.push(Rc::new(IdentifierMatcher));
+        lexer.matchers.push(Rc::new(ConstantStringMatcher::new(
+            Operator,
+            &[
+                "==", "!=", "<=", ">="
+            ]
+        )));
+
         lexer.matchers.push(Rc::new(ConstantCharMatcher::new(
             Symbol,
             &[
-                ':', '!', '(', ')', '.', '=', '#', ',', '@'
+                ':', '!', '(', ')', '.', '=', '#', ',', '@',
+                '>', '<', '~', '[', ']', '$', '|', '&', '{', '}'
             ]
         )));
 
@@ -50,7 +60,7 @@ impl<'l> Lexer<'l> {
         lexer
     }
 
-    pub fn match_token(&mut self) -> Result<Option<Token>, ()> {
+    pub fn match_token(&mut self) -> Result<Option<Token>, Diagnostic> {
         for matcher in &mut self.matchers {
             match self.tokenizer.try_match_token(matcher.as_ref())? {
                 Some(t) => return Ok(Some(t)),
@@ -63,9 +73,9 @@ impl<'l> Lexer<'l> {
 }
 
 impl<'l> Iterator for Lexer<'l> {
-    type Item = Result<Token, ()>;
+    type Item = Result<Token, Diagnostic>;
 
-    fn next(&mut self) -> Option<Result<Token, ()>> {
+    fn next(&mut self) -> Option<Result<Token, Diagnostic>> {
         let token: Token = match self.match_token() {
             Ok(result) => match result {
                 Some(n) => n,
@@ -74,9 +84,9 @@ impl<'l> Iterator for Lexer<'l> {
 
                     return Some(
                         Err(
-                            response!(
-                                Wrong("weird character here :("),
-                                self.source.file,
+                            Diagnostic::error(
+                                "weird character here :(",
+                                &self.source.file,
                                 Pos(
                                     (
                                         pos.0,
@@ -92,7 +102,7 @@ impl<'l> Iterator for Lexer<'l> {
                 }
             },
 
-            _ => return Some(Err(()))
+            Err(diagnostic) => return Some(Err(diagnostic))
         };
 
         match token.token_type {
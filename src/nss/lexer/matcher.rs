@@ -1,4 +1,4 @@
-use super::super::error::Response::*;
+use super::super::error::Diagnostic;
 use super::*;
 
 macro_rules! token {
@@ -38,14 +38,14 @@ macro_rules! token {
 }
 
 pub trait Matcher<'t> {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()>;
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic>;
 }
 
 pub struct CommentMatcher;
 
 impl<'t> Matcher<'t> for CommentMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
-        if tokenizer.peek_range(2).unwrap_or_else(String::new) == "--" {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
+        if tokenizer.peek_range(2).unwrap_or_default() == "--" {
             while !tokenizer.is_end() && tokenizer.peek() != Some('\n') {
                 tokenizer.advance()
             }
@@ -72,7 +72,7 @@ impl ConstantStringMatcher {
 }
 
 impl<'t> Matcher<'t> for ConstantStringMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         for constant in self.constants {
             let len = constant.len();
             let c = match tokenizer.peek_range(len) {
@@ -113,7 +113,7 @@ impl ConstantCharMatcher {
 }
 
 impl<'t> Matcher<'t> for ConstantCharMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let c = tokenizer.peek().unwrap();
 
         for constant in self.constants {
@@ -137,7 +137,7 @@ impl<'t> Matcher<'t> for ConstantCharMatcher {
 pub struct StringLiteralMatcher;
 
 impl<'t> Matcher<'t> for StringLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let mut raw_marker = false;
 
         let mut pos = tokenizer.pos;
@@ -154,9 +154,9 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
 
                     '"'
                 } else if tokenizer.peek_n(1) == Some('\'') {
-                    return Err(response!(
-                        Wrong("no such thing as a raw character literal"),
-                        tokenizer.source.file,
+                    return Err(Diagnostic::error(
+                        "no such thing as a raw character literal",
+                        &tokenizer.source.file,
                         Pos(
                             (
                                 pos.0,
@@ -184,9 +184,9 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
 
         loop {
             if tokenizer.is_end() {
-                return Err(response!(
-                    Wrong(format!("unterminated delimeter `{}`", delimeter)),
-                    tokenizer.source.file,
+                return Err(Diagnostic::error(
+                    format!("unterminated delimeter `{}`", delimeter),
+                    &tokenizer.source.file,
                     Pos(
                         (
                             pos.0 + 1,
@@ -215,9 +215,9 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
                     'r' => '\r',
                     't' => '\t',
                     escaped => {
-                        return Err(response!(
-                            Wrong(format!("unexpected escape character: {}", escaped)),
-                            tokenizer.source.file,
+                        return Err(Diagnostic::error(
+                            format!("unexpected escape character: {}", escaped),
+                            &tokenizer.source.file,
                             Pos(
                                 (
                                     tokenizer.pos.0,
@@ -245,7 +245,7 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
                     // check for valid closing delimeter and alternative
                     c => {
                         if c == delimeter {
-                            if string.len() > 0 && string != " " {
+                            if !string.is_empty() && string != " " {
                                 break;
                             } else {
                                 string.push(tokenizer.next().unwrap())
@@ -274,13 +274,54 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
     }
 }
 
+// an unquoted `url(…)` like `url(https://example.com/a.css?b=c)` is kept as written,
+// `url(@image)` and `url("a.png")` are left to the other matchers
+pub struct UrlMatcher;
+
+impl<'t> Matcher<'t> for UrlMatcher {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
+        if tokenizer.peek_range(4).unwrap_or_default() != "url(" {
+            return Ok(None)
+        }
+
+        tokenizer.advance_n(4);
+
+        let mut accum = String::from("url(");
+
+        while let Some(c) = tokenizer.peek() {
+            if c == ')' {
+                break
+            }
+
+            if c.is_whitespace() || "\"'()@".contains(c) {
+                return Ok(None)
+            }
+
+            accum.push(c);
+            tokenizer.advance()
+        }
+
+        if tokenizer.peek() != Some(')') || accum.len() == 4 || accum.contains("#{") {
+            return Ok(None)
+        }
+
+        tokenizer.advance();
+        accum.push(')');
+
+        Ok(Some(token!(tokenizer, Identifier, accum)))
+    }
+}
+
 pub struct IdentifierMatcher;
 
 impl<'t> Matcher<'t> for IdentifierMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let peeked = tokenizer.peek().unwrap();
 
-        if !peeked.is_alphabetic() && peeked != '_' {
+        // vendor prefixes like `-webkit-transition`
+        let prefixed = peeked == '-' && tokenizer.peek_n(1).is_some_and(|c| c.is_alphabetic());
+
+        if !peeked.is_alphabetic() && peeked != '_' && !prefixed {
             return Ok(None);
         }
 
@@ -297,11 +338,13 @@ impl<'t> Matcher<'t> for IdentifierMatcher {
 pub struct NumberLiteralMatcher;
 
 impl<'t> Matcher<'t> for NumberLiteralMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let mut accum = String::new();
 
+        let start = tokenizer.pos;
+
         let curr = tokenizer.next().unwrap();
-        if curr.is_digit(10) {
+        if curr.is_ascii_digit() {
             accum.push(curr)
         } else if curr == '.' {
             accum.push_str("0.")
@@ -313,13 +356,13 @@ impl<'t> Matcher<'t> for NumberLiteralMatcher {
 
         while !tokenizer.is_end() {
             let current = tokenizer.peek().unwrap();
-            if !current.is_whitespace() && current.is_digit(10) || current == '.' {
+            if !current.is_whitespace() && current.is_ascii_digit() || current == '.' {
                 if current == '.' && accum.contains('.') {
                     let pos = tokenizer.pos;
 
-                    return Err(response!(
-                        Wrong("unexpected extra decimal point"),
-                        tokenizer.source.file,
+                    return Err(Diagnostic::error(
+                        "unexpected extra decimal point",
+                        &tokenizer.source.file,
                         Pos(
                             (
                                 pos.0,
@@ -341,24 +384,90 @@ impl<'t> Matcher<'t> for NumberLiteralMatcher {
         }
 
         if ["-", "-0.", "-.", "0."].contains(&accum.as_str()) {
-            Ok(None)
-        } else {
-            if accum.contains(".") {
-                let literal: String = match accum.parse::<f64>() {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse float `{}`: {}", accum, error),
-                };
+            return Ok(None)
+        }
 
-                Ok(Some(token!(tokenizer, Float, literal)))
-            } else {
-                let literal: String = match accum.parse::<f64>() {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse int `{}`: {}", accum, error),
-                };
+        // `1.5e3` and `1e-3`, an `e` without digits after it is a unit like `em`
+        if matches!(tokenizer.peek(), Some('e') | Some('E')) {
+            let sign  = matches!(tokenizer.peek_n(1), Some('+') | Some('-'));
+            let digit = tokenizer.peek_n(if sign { 2 } else { 1 });
+
+            if digit.is_some_and(|c| c.is_ascii_digit()) {
+                accum.push(tokenizer.next().unwrap());
 
-                Ok(Some(token!(tokenizer, Int, literal)))
+                if sign {
+                    accum.push(tokenizer.next().unwrap())
+                }
+
+                accum.push_str(&tokenizer.collect_while(|c| c.is_ascii_digit()))
             }
         }
+
+        let literal: String = match accum.parse::<f64>() {
+            Ok(result) if result.is_finite() => result.to_string(),
+
+            // `1e309` is past what a float holds
+            Ok(_) => return Err(Diagnostic::error(
+                format!("`{}` is too large to be a number", accum),
+                &tokenizer.source.file,
+                Pos(
+                    (
+                        start.0,
+                        tokenizer
+                            .source
+                            .lines
+                            .get(start.0.saturating_sub(1))
+                            .unwrap_or(tokenizer.source.lines.last().unwrap())
+                            .to_string()
+                    ),
+                    (start.1 + 1, start.1 + accum.len()),
+                )
+            )),
+
+            Err(error) => panic!("unable to parse number `{}`: {}", accum, error),
+        };
+
+        // `12px`, `1.5rem`, `50%` - the unit has to follow the number directly
+        let unit = if tokenizer.peek() == Some('%') {
+            tokenizer.advance();
+
+            String::from("%")
+        } else {
+            tokenizer.collect_while(|c| c.is_alphabetic())
+        };
+
+        if !unit.is_empty() {
+            Ok(Some(token!(tokenizer, Dimension, format!("{}{}", literal, unit))))
+        } else if accum.contains('.') || literal.contains('.') {
+            Ok(Some(token!(tokenizer, Float, literal)))
+        } else {
+            Ok(Some(token!(tokenizer, Int, literal)))
+        }
+    }
+}
+
+pub struct HexColorMatcher;
+
+impl<'t> Matcher<'t> for HexColorMatcher {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
+        if tokenizer.peek() != Some('#') {
+            return Ok(None);
+        }
+
+        tokenizer.advance();
+
+        // whole word, so `#bad-news` stays a `#` followed by an identifier
+        let digits = tokenizer.collect_while(|c| c.is_alphanumeric() || "_-".contains(c));
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+
+        let mut token = token!(tokenizer, Color, digits);
+
+        token.slice.1 += 1; // the `#`
+
+        Ok(Some(token))
     }
 }
 
@@ -377,7 +486,7 @@ impl KeyMatcher {
 }
 
 impl<'t> Matcher<'t> for KeyMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         for constant in self.constants {
             if let Some(s) = tokenizer.peek_range(constant.len()) {
                 if s == *constant {
@@ -404,7 +513,7 @@ impl<'t> Matcher<'t> for KeyMatcher {
 pub struct EOLMatcher;
 
 impl<'t> Matcher<'t> for EOLMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         if tokenizer.peek() == Some('\n') {
             tokenizer.pos.0 += 1;
             tokenizer.pos.1 = 0;
@@ -420,7 +529,7 @@ impl<'t> Matcher<'t> for EOLMatcher {
 pub struct WhitespaceMatcher;
 
 impl<'t> Matcher<'t> for WhitespaceMatcher {
-    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
+    fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, Diagnostic> {
         let string = tokenizer.collect_while(|c| c.is_whitespace() && c != '\n');
 
         if !string.is_empty() {
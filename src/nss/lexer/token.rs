@@ -9,6 +9,8 @@ pub enum TokenType {
     Str,
     Float,
     Int,
+    Dimension,
+    Color,
     Whitespace,
     EOL,
     EOF,
@@ -25,6 +27,8 @@ impl fmt::Display for TokenType {
             Str        => write!(f, "string"),
             Float      => write!(f, "float"),
             Int        => write!(f, "int"),
+            Dimension  => write!(f, "dimension"),
+            Color      => write!(f, "color"),
             Whitespace => write!(f, "whitespace"),
             EOL        => write!(f, "new-line"),
             EOF        => write!(f, "end of file"),
@@ -36,44 +40,59 @@ impl fmt::Display for TokenType {
 pub struct Pos(pub (usize, String), pub (usize, usize));
 
 impl Pos {
+    // for things that went wrong outside of any line, like a missing file
+    pub fn none() -> Self {
+        Pos((0, String::new()), (0, 0))
+    }
+
     pub fn get_lexeme(&self) -> String {
         (self.0).1[(self.1).0 - if (self.1).0 > 0 { 1 } else { 0 } .. (self.1).1].to_string()
     }
-}
 
-impl fmt::Display for Pos {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let linepad = format!("{:5} |", " ").blue().bold();
-        let line_n  = format!("{:5} | ", (self.0).0).blue().bold();
+    /// The line with the span marked below it, `color` for a terminal.
+    pub fn render(&self, color: bool) -> String {
+        let paint = |text: String, name: &str| if color { text.color(name).bold().to_string() } else { text };
+
+        let linepad = paint(format!("{:5} |", " "), "blue");
+        let line_n  = paint(format!("{:5} | ", (self.0).0), "blue");
 
-        let mut mark = (self.0).1[(self.1).0.saturating_sub(1) .. (self.1).1].to_string();
+        let line  = &(self.0).1;
+        let end   = (self.1).1.min(line.len());
+        let start = (self.1).0.saturating_sub(1).min(end);
+
+        let mut mark = line[start .. end].to_string();
 
         if mark.split_whitespace().count() == 0 {
-            mark = format!("{:->count$}", "->".red().bold(), count = mark.len())
+            mark = paint(format!("{:->count$}", "->", count = mark.len()), "red")
         } else {
-            mark = format!("{}", mark.red().bold())
+            mark = paint(mark, "red")
         }
 
         let mut arrows = format!("{: <count$}", " ", count = (self.1).0);
 
-        for _ in 0 .. (self.1).1 - (self.1).0 + 1 {
+        for _ in 0 .. ((self.1).1 + 1).saturating_sub((self.1).0) {
             arrows.push('^')
         }
 
-        write!(
-            f,
+        format!(
             "\n{}\n{}{}{}{}\n{}{}",
             linepad,
             line_n,
-            &(self.0).1[..(self.1).0.saturating_sub(1)],
+            &line[.. start],
             mark,
-            &(self.0).1[(self.1).1 ..],
+            &line[end ..],
             linepad,
-            arrows.red().bold(),
+            paint(arrows, "red"),
         )
     }
 }
 
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
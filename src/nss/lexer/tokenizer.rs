@@ -1,5 +1,6 @@
 use super::token::*;
 use super::{ Matcher, Source };
+use super::super::error::Diagnostic;
 
 pub struct Snapshot {
     pub index: usize,
@@ -36,7 +37,7 @@ impl<'a> Tokenizer<'a> {
     }
 
     pub fn advance(&mut self) {
-        if let Some(item) = self.items.get(self.index + 1) {
+        if self.items.get(self.index + 1).is_some() {
             self.pos.1 += 1
         }
 
@@ -90,7 +91,7 @@ impl<'a> Tokenizer<'a> {
             .pos
     }
 
-    pub fn try_match_token(&mut self, matcher: &Matcher<'a>) -> Result<Option<Token>, ()> {
+    pub fn try_match_token(&mut self, matcher: &dyn Matcher<'a>) -> Result<Option<Token>, Diagnostic> {
         if self.is_end() {
             return Ok(
                 Some(
@@ -98,7 +99,7 @@ impl<'a> Tokenizer<'a> {
                         TokenType::EOF,
                         (
                             self.pos.0,
-                            if self.source.lines.len() > 0 {
+                            if !self.source.lines.is_empty() {
                                 self.source
                                     .lines
                                     .get(self.pos.0)
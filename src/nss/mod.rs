@@ -1,4 +1,3 @@
-#[macro_use]
 pub mod error;
 pub mod source;
 pub mod lexer;
@@ -6,9 +6,32 @@ use super::*;
 #[derive(Debug, Clone, PartialEq)]
 pub enum StatementNode {
     Expression(Expression),
-    Definition(Vec<String>, Vec<Statement>),
-    Style(String, Expression),
-    Var(String, Expression)
+    Definition(Vec<Selector>, Vec<Statement>),
+    Style(Interpolation, Expression),
+    Media(Vec<MediaQuery>, Vec<Statement>),
+    Mixin(String, Vec<Parameter>, Vec<Statement>),
+    Include(Option<String>, String, Vec<Argument>, Option<Vec<Statement>>), // namespace, name, arguments and an optional content block
+    Content,
+    Function(String, Vec<Parameter>, Vec<Statement>),
+    Return(Expression),
+    If(Vec<(Expression, Vec<Statement>)>, Option<Vec<Statement>>), // `@else if`s and an `@else`
+    Each(Vec<String>, Expression, Vec<Statement>),
+    For(String, Expression, Expression, bool, Vec<Statement>), // inclusive with `through`, exclusive with `to`
+    Var(String, Expression, bool, bool), // `!global` and `!default`
+    Use(String, Option<String>),         // path and namespace, `*` for none
+    Import(String, Vec<MediaQuery>),     // plain CSS, `"a.css"` or `url(a.css)` as written
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Option<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    pub name: Option<String>, // `@size: 2px` passes by name
+    pub value: Expression,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -26,17 +49,44 @@ impl Statement {
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExpressionNode {
     Int(i32),
-    Float(f32),
+    Float(f64),
+    Dimension(f64, String),
     Str(String),
+    InterpolatedStr(Interpolation), // `"icon-#{@name}.svg"`
     Identifier(String),
+    Interpolation(Interpolation),   // `#{@size}px`, unquoted
     Color(String),
-    Call(Rc<Expression>, Vec<Expression>),
+    Call(Rc<Expression>, Vec<Argument>),
     Binary(Rc<Expression>, Operator, Rc<Expression>),
+    Neg(Rc<Expression>),
+    Not(Rc<Expression>),
+    List(Vec<Expression>, Separator),
+    Map(Vec<(Expression, Expression)>),
     Important(Rc<Expression>),
     Deref(String),
+    Member(String, Rc<Expression>), // `tokens.@primary`, a variable or function of a module
     EOF,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Separator {
+    Space,
+    Comma,
+    Slash, // `font: 12px/1.5`
+}
+
+impl Separator {
+    pub fn as_str(&self) -> &str {
+        use self::Separator::*;
+
+        match *self {
+            Space => " ",
+            Comma => ", ",
+            Slash => "/",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Expression { // bruh
     pub node: ExpressionNode,
@@ -47,24 +97,49 @@ impl Expression {
     pub fn new(node: ExpressionNode, pos: Pos) -> Self {
         Self { node, pos }
     }
+
+    pub fn is_number(&self) -> bool {
+        use self::ExpressionNode::*;
+
+        matches!(self.node, Int(_) | Float(_) | Dimension(..))
+    }
+
+    // what a plain css slash like `1 / 2` or `header / main` separates
+    pub fn is_slash_operand(&self) -> bool {
+        use self::ExpressionNode::*;
+
+        self.is_number() || matches!(self.node, Identifier(_) | List(_, Separator::Slash))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Operator {
-    Add, Sub, Mul, Div, Pow
+    Add, Sub, Mul, Div, Pow,
+    Eq, NotEq, Lt, Gt, LtEq, GtEq,
+    And, Or,
 }
 
 impl Operator {
+    // with the precedence, which `FromStr` has no room for
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(operator: &str) -> Option<(Operator, u8)> {
         use self::Operator::*;
 
         let precedence = match operator {
-            "+" => (Add, 0),
-            "-" => (Sub, 0),
-            "*" => (Mul, 1),
-            "/" => (Div, 1),
-            "^" => (Pow, 2),
-            _   => return None
+            "or"  => (Or, 0),
+            "and" => (And, 1),
+            "=="  => (Eq, 2),
+            "!="  => (NotEq, 2),
+            "<"   => (Lt, 3),
+            ">"   => (Gt, 3),
+            "<="  => (LtEq, 3),
+            ">="  => (GtEq, 3),
+            "+"   => (Add, 4),
+            "-"   => (Sub, 4),
+            "*"   => (Mul, 5),
+            "/"   => (Div, 5),
+            "^"   => (Pow, 6),
+            _     => return None
         };
 
         Some(precedence)
@@ -74,17 +149,231 @@ impl Operator {
         use self::Operator::*;
 
         match *self {
-            Add => "+",
-            Sub => "-",
-            Mul => "*",
-            Div => "/",
-            Pow => "^"
+            Add   => "+",
+            Sub   => "-",
+            Mul   => "*",
+            Div   => "/",
+            Pow   => "^",
+            Eq    => "==",
+            NotEq => "!=",
+            Lt    => "<",
+            Gt    => ">",
+            LtEq  => "<=",
+            GtEq  => ">=",
+            And   => "and",
+            Or    => "or",
         }
     }
+
+    pub fn is_comparison(&self) -> bool {
+        use self::Operator::*;
+
+        matches!(*self, Lt | Gt | LtEq | GtEq)
+    }
 }
 
 impl fmt::Display for Operator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.as_str())
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling,
+}
+
+impl fmt::Display for Combinator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Combinator::*;
+
+        match *self {
+            Descendant        => write!(f, " "),
+            Child             => write!(f, " > "),
+            NextSibling       => write!(f, " + "),
+            SubsequentSibling => write!(f, " ~ "),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Expression(Expression), // `#{@name}`
+}
+
+/// A name that may contain interpolations, like `mt-#{@i}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpolation(pub Vec<Segment>);
+
+impl Interpolation {
+    pub fn text(text: &str) -> Self {
+        Interpolation(vec![Segment::Text(text.to_string())])
+    }
+}
+
+impl fmt::Display for Interpolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for segment in self.0.iter() {
+            match *segment {
+                Segment::Text(ref text) => write!(f, "{}", text)?,
+                Segment::Expression(_)  => write!(f, "#{{…}}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSelector {
+    pub name: String,
+    pub matcher: Option<(String, String)>, // operator and value, e.g. `^=` and `"https"`
+    pub flag: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PseudoArgument {
+    Selectors(Vec<Selector>), // :not(.a, .b)
+    Raw(String),              // :nth-child(2n+1)
+}
+
+impl fmt::Display for PseudoArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PseudoArgument::Selectors(ref selectors) => write!(f, "{}", join(selectors, ", ")),
+            PseudoArgument::Raw(ref raw)             => write!(f, "{}", raw),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimpleSelector {
+    Parent(Option<Interpolation>), // `&`, or `&-suffix`
+    Universal,
+    Type(Interpolation),
+    Class(Interpolation),
+    Id(Interpolation),
+    Attribute(AttributeSelector),
+    PseudoClass(String, Option<PseudoArgument>),
+    PseudoElement(String, Option<PseudoArgument>),
+}
+
+impl fmt::Display for SimpleSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::SimpleSelector::*;
+
+        match *self {
+            Parent(None)   => write!(f, "&"),
+            Parent(Some(ref suffix)) => write!(f, "&{}", suffix),
+            Universal      => write!(f, "*"),
+            Type(ref n)    => write!(f, "{}", n),
+            Class(ref n)   => write!(f, ".{}", n),
+            Id(ref n)      => write!(f, "#{}", n),
+            Attribute(ref attribute) => {
+                write!(f, "[{}", attribute.name)?;
+
+                if let Some((ref operator, ref value)) = attribute.matcher {
+                    write!(f, "{}{}", operator, value)?
+                }
+
+                if let Some(ref flag) = attribute.flag {
+                    write!(f, " {}", flag)?
+                }
+
+                write!(f, "]")
+            },
+            PseudoClass(ref n, ref argument) | PseudoElement(ref n, ref argument) => {
+                let colons = if let PseudoElement(..) = *self { "::" } else { ":" };
+
+                match *argument {
+                    Some(ref argument) => write!(f, "{}{}({})", colons, n, argument),
+                    None               => write!(f, "{}{}", colons, n),
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectorPart {
+    Compound(Vec<SimpleSelector>),
+    Combinator(Combinator),
+}
+
+/// A complex selector like `ul > li.item:hover`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector(pub Vec<SelectorPart>);
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+
+        for part in self.0.iter() {
+            match *part {
+                SelectorPart::Compound(ref simples) => {
+                    for simple in simples.iter() {
+                        out.push_str(&simple.to_string())
+                    }
+                },
+
+                SelectorPart::Combinator(ref combinator) => out.push_str(&combinator.to_string()),
+            }
+        }
+
+        write!(f, "{}", out.trim())
+    }
+}
+
+impl Selector {
+    /// Without spaces around combinators, for compressed output.
+    pub fn to_compressed(&self) -> String {
+        let mut out = String::new();
+
+        for part in self.0.iter() {
+            match *part {
+                SelectorPart::Compound(ref simples) => {
+                    for simple in simples.iter() {
+                        out.push_str(&simple.to_string())
+                    }
+                },
+
+                SelectorPart::Combinator(Combinator::Descendant) => out.push(' '),
+                SelectorPart::Combinator(ref combinator) => out.push_str(combinator.to_string().trim()),
+            }
+        }
+
+        out.trim().to_string()
+    }
+}
+
+pub fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+    items.iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaFeature {
+    Boolean(Interpolation),           // (color)
+    Plain(Interpolation, Expression), // (min-width: @tablet)
+    Range(                            // (400px <= width < 700px)
+        Option<(Expression, String)>,
+        String,
+        Option<(String, Expression)>
+    ),
+    Interpolated(Interpolation),      // #{@query}, used as written
+}
+
+/// `only screen and (min-width: 768px)`, features are joined by `and`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    pub modifier: Option<String>, // `not` or `only`
+    pub media_type: Option<String>,
+    pub features: Vec<MediaFeature>,
+}
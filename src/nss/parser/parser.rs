@@ -1,14 +1,21 @@
-use super::super::error::Response::Wrong;
+use super::super::error::{ Diagnostic, Diagnostics, Severity };
 use super::*;
 
 use std::rc::Rc;
 
+// past this many errors the rest is mostly noise from the first ones
+pub const MAX_ERRORS: usize = 20;
+
 pub struct Parser<'a> {
     index: usize,
     tokens: Vec<Token>,
     source: &'a Source,
     indent_base: usize,
     indent: usize,
+    depth: usize, // of parentheses around the current expression
+    statement_indent: usize, // of the line the current statement starts on
+    diagnostics: Diagnostics,
+    max_errors: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -19,20 +26,92 @@ impl<'a> Parser<'a> {
             index: 0,
             indent_base: 0,
             indent: 0,
+            depth: 0,
+            statement_indent: 0,
+            diagnostics: Diagnostics::new(),
+            max_errors: MAX_ERRORS,
+        }
+    }
+
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors.max(1);
+        self
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Statement>, Diagnostics> {
+        let (ast, diagnostics) = self.parse_partial();
+
+        if diagnostics.has_errors() {
+            Err(diagnostics)
+        } else {
+            Ok(ast)
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>, ()> {
+    /// Parses as much as possible, skipping statements that went wrong.
+    pub fn parse_partial(&mut self) -> (Vec<Statement>, Diagnostics) {
         let mut ast = Vec::new();
 
+        if self.tokens.is_empty() {
+            return (ast, std::mem::take(&mut self.diagnostics))
+        }
+
+        let _ = self.next_newline();
+
+        while self.remaining() > 0 && !self.is_exhausted() {
+            let indent = self.get_indent();
+
+            match self.parse_statement().and_then(|statement| { self.next_newline()?; Ok(statement) }) {
+                Ok(statement)   => ast.push(statement),
+                Err(diagnostic) => self.recover(diagnostic, indent),
+            }
+        }
+
+        (ast, std::mem::take(&mut self.diagnostics))
+    }
+
+    // records what went wrong and skips to the next line that isn't part of the broken statement
+    fn recover(&mut self, diagnostic: Diagnostic, indent: usize) {
+        self.diagnostics.push(diagnostic);
+        self.depth = 0;
+
+        if self.is_exhausted() {
+            let position = self.current_position();
+
+            self.diagnostics.push(
+                Diagnostic::new(
+                    Severity::Note,
+                    format!("stopped after {} errors", self.max_errors),
+                    &self.source.file,
+                    position
+                )
+            );
+
+            self.index = self.tokens.len();
+
+            return
+        }
+
         while self.remaining() > 0 {
-            ast.push(self.parse_statement()?)
+            if self.current_lexeme() == "\n" {
+                while self.current_lexeme() == "\n" && self.remaining() > 0 {
+                    self.index += 1
+                }
+
+                if self.remaining() == 0 || self.get_indent() <= indent {
+                    break
+                }
+            } else {
+                self.index += 1
+            }
         }
+    }
 
-        Ok(ast)
+    fn is_exhausted(&self) -> bool {
+        self.diagnostics.error_count() >= self.max_errors
     }
 
-    pub fn parse_statement(&mut self) -> Result<Statement, ()> {
+    pub fn parse_statement(&mut self) -> Result<Statement, Diagnostic> {
         use self::TokenType::*;
 
         while self.current_type() == EOL && self.remaining() != 0 {
@@ -41,25 +120,73 @@ impl<'a> Parser<'a> {
 
         let position = self.current_position();
 
+        self.statement_indent = self.get_indent();
+
         let statement = match self.current_type() {
-            Symbol => match self.current_lexeme().as_str() {
-                "@" => {
+            Symbol if self.current_lexeme() == "@" => {
+                self.next()?;
+
+                let name = self.eat_type(&Identifier)?;
+
+                if self.current_lexeme() == "=" {
                     self.next()?;
 
-                    let name = self.eat_type(&Identifier)?;
+                    let value = self.parse_expression()?;
+
+                    let mut global  = false;
+                    let mut default = false;
 
-                    if self.current_lexeme() == "=" {
+                    while self.current_lexeme() == "!" && self.remaining() > 0 {
                         self.next()?;
 
-                        Statement::new(
-                            StatementNode::Var(
-                                name,
-                                self.parse_expression()?
-                            ),
+                        match self.current_lexeme().as_str() {
+                            "global"  => global = true,
+                            "default" => default = true,
+
+                            flag => return Err(
+                                Diagnostic::error(
+                                    format!("unknown flag `!{}`, expected `!global` or `!default`", flag),
+                                    &self.source.file,
+                                    self.current_position()
+                                )
+                            )
+                        }
+
+                        self.next()?
+                    }
+
+                    Statement::new(
+                        StatementNode::Var(name, value, global, default),
+                        self.span_from(position)
+                    )
+                } else {
+                    match name.as_str() {
+                        "media"   => return self.parse_media(position),
+                        "mixin"   => return self.parse_mixin(position),
+                        "include" => return self.parse_include(position),
+                        "function" => return self.parse_function(position),
+                        "if"       => return self.parse_if(position),
+                        "each"     => return self.parse_each(position),
+                        "for"      => return self.parse_for(position),
+                        "use"      => return self.parse_use(position),
+                        "import"   => return self.parse_import(position),
+                        "else"     => return Err(
+                            Diagnostic::error(
+                                "`@else` without an `@if` right before it",
+                                &self.source.file,
+                                position.clone()
+                            )
+                        ),
+                        "return"   => Statement::new(
+                            StatementNode::Return(self.parse_expression()?),
                             self.span_from(position)
-                        )
-                    } else {
-                        Statement::new(
+                        ),
+                        "content"  => Statement::new(
+                            StatementNode::Content,
+                            self.span_from(position)
+                        ),
+
+                        _ => Statement::new(
                             StatementNode::Expression(
                                 Expression::new(
                                     ExpressionNode::Deref(
@@ -72,76 +199,58 @@ impl<'a> Parser<'a> {
                         )
                     }
                 }
+            },
 
-                c => return Err(
-                    response!(
-                        Wrong(format!("unexpected symbol: `{}`", c)),
-                        self.source.file,
-                        self.current_position()
-                    )
-                )
-            }
-            Identifier => {
-                let mut names = Vec::new();
+            // `+name` includes a mixin, `+ name` is a sibling combinator
+            Operator if self.current_lexeme() == "+" && self.is_adjacent() => {
+                self.next()?;
 
-                names.push(self.eat()?);
+                return self.parse_include(position)
+            },
 
-                while self.current_lexeme() == "," {
-                    self.next()?;
+            _ if self.is_rule_header() => {
+                let selectors = self.parse_selector_list()?;
 
-                    names.push(self.eat_type(&Identifier)?)
+                if self.current_lexeme() != "\n" {
+                    return Err(
+                        Diagnostic::error(
+                            format!("unexpected `{}` in selector", self.current_lexeme()),
+                            &self.source.file,
+                            self.current_position()
+                        )
+                    )
                 }
 
-                match self.current_lexeme().as_str() {
-                    ":" => {
-                        if names.len() > 1 {
-                            return Err(
-                                response!(
-                                    Wrong("this makes no sense (many attributes)"),
-                                    self.source.file,
-                                    self.current_position()
-                                )
-                            )
-                        }
-
-                        self.next()?;
+                self.next()?;
 
-                        Statement::new(
-                            StatementNode::Style(
-                                names[0].clone(),
-                                self.parse_expression()?
-                            ),
-                            self.span_from(position)
-                        )
-                    },
+                let definitions = self.parse_body()?;
 
-                    "\n" => {
-                        self.eat_lexeme("\n")?;
+                return Ok(
+                    Statement::new(
+                        StatementNode::Definition(selectors, definitions),
+                        self.span_from(position)
+                    )
+                )
+            },
 
-                        let definitions = self.parse_body()?;
+            _ if self.current_type() == Identifier || self.starts_interpolation() => {
+                let name = self.parse_interpolation()?;
 
-                        return Ok(
-                            Statement::new(
-                                StatementNode::Definition(names, definitions),
-                                self.span_from(position)
-                            )
-                        )
-                    },
+                self.eat_lexeme(":")?;
 
-                    c => return Err(
-                        response!(
-                            Wrong(format!("unexpected token: `{}`", c)),
-                            self.source.file,
-                            self.current_position()
-                        )
-                    )
-                }
+                Statement::new(
+                    StatementNode::Style(
+                        name,
+                        self.parse_expression()?
+                    ),
+                    self.span_from(position)
+                )
             },
 
             c => return Err(
-                response!(
-                    Wrong(format!("unexpected token: `{}`", c)),
-                    self.source.file,
+                Diagnostic::error(
+                    format!("unexpected token: `{}`", c),
+                    &self.source.file,
                     self.current_position()
                 )
             )
@@ -154,51 +263,998 @@ impl<'a> Parser<'a> {
         Ok(statement)
     }
 
-    fn parse_body(&mut self) -> Result<Vec<Statement>, ()> {
+    fn parse_body(&mut self) -> Result<Vec<Statement>, Diagnostic> {
         let backup_indent = self.indent;
-
-        self.indent = self.get_indent();
+        let indent = self.get_indent();
 
         if self.indent_base == 0 {
-            self.indent_base = self.indent
+            self.indent_base = indent
+        } else if !indent.is_multiple_of(self.indent_base) {
+            return Err(
+                Diagnostic::error(
+                    "inconsistent indentation is not cool",
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        }
+
+        self.indent = indent;
+
+        let mut accum = Vec::new();
+
+        while !self.is_dedent() && self.remaining() > 0 && !self.is_exhausted() {
+            match self.parse_statement().and_then(|statement| { self.next_newline()?; Ok(statement) }) {
+                Ok(statement)   => accum.push(statement),
+                Err(diagnostic) => self.recover(diagnostic, indent),
+            }
+        }
+        
+        self.indent = backup_indent;
+
+        Ok(accum)
+    }
+
+    fn parse_media(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let queries = self.parse_media_query_list()?;
+
+        if self.current_lexeme() != "\n" {
+            return Err(
+                Diagnostic::error(
+                    format!("unexpected `{}` in media query", self.current_lexeme()),
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        }
+
+        self.next()?;
+
+        let body = self.parse_body()?;
+
+        Ok(
+            Statement::new(
+                StatementNode::Media(queries, body),
+                self.span_from(position)
+            )
+        )
+    }
+
+    fn parse_mixin(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let name       = self.eat_type(&TokenType::Identifier)?;
+        let parameters = self.parse_parameters()?;
+
+        self.eat_lexeme("\n")?;
+
+        let body = self.parse_body()?;
+
+        Ok(
+            Statement::new(
+                StatementNode::Mixin(name, parameters, body),
+                self.span_from(position)
+            )
+        )
+    }
+
+    fn parse_if(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let column       = (position.1).0;
+        let mut branches = Vec::new();
+        let mut fallback = None;
+
+        let condition = self.parse_expression()?;
+
+        self.eat_lexeme("\n")?;
+
+        branches.push((condition, self.parse_body()?));
+
+        // `@else` has to line up with its `@if`
+        while self.remaining() > 0
+            && self.current_lexeme() == "@"
+            && self.current().slice.0 == column
+            && self.tokens.get(self.index + 1).is_some_and(|t| t.lexeme == "else")
+        {
+            self.next()?;
+            self.next()?;
+
+            if self.current_lexeme() == "if" {
+                self.next()?;
+
+                let condition = self.parse_expression()?;
+
+                self.eat_lexeme("\n")?;
+
+                branches.push((condition, self.parse_body()?))
+            } else {
+                self.eat_lexeme("\n")?;
+
+                fallback = Some(self.parse_body()?);
+
+                break
+            }
+        }
+
+        Ok(
+            Statement::new(
+                StatementNode::If(branches, fallback),
+                self.span_from(position)
+            )
+        )
+    }
+
+    fn parse_each(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let mut names = Vec::new();
+
+        loop {
+            self.eat_lexeme("@")?;
+
+            names.push(self.eat_type(&TokenType::Identifier)?);
+
+            if self.current_lexeme() != "," {
+                break
+            }
+
+            self.next()?
+        }
+
+        self.eat_lexeme("in")?;
+
+        let list = self.parse_expression()?;
+
+        self.eat_lexeme("\n")?;
+
+        Ok(
+            Statement::new(
+                StatementNode::Each(names, list, self.parse_body()?),
+                self.span_from(position)
+            )
+        )
+    }
+
+    fn parse_for(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        self.eat_lexeme("@")?;
+
+        let name = self.eat_type(&TokenType::Identifier)?;
+
+        self.eat_lexeme("from")?;
+
+        let from = self.parse_arithmetic()?;
+
+        let inclusive = match self.current_lexeme().as_str() {
+            "through" => true,
+            "to"      => false,
+
+            c => return Err(
+                Diagnostic::error(
+                    format!("expected `through` or `to`, found `{}`", c),
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        };
+
+        self.next()?;
+
+        let to = self.parse_arithmetic()?;
+
+        self.eat_lexeme("\n")?;
+
+        Ok(
+            Statement::new(
+                StatementNode::For(name, from, to, inclusive, self.parse_body()?),
+                self.span_from(position)
+            )
+        )
+    }
+
+    fn parse_function(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let name       = self.eat_type(&TokenType::Identifier)?;
+        let parameters = self.parse_parameters()?;
+
+        self.eat_lexeme("\n")?;
+
+        let body = self.parse_body()?;
+
+        Ok(
+            Statement::new(
+                StatementNode::Function(name, parameters, body),
+                self.span_from(position)
+            )
+        )
+    }
+
+    // `(@a, @b: 10px)`, the parentheses are optional without parameters
+    fn parse_parameters(&mut self) -> Result<Vec<Parameter>, Diagnostic> {
+        let mut parameters = Vec::new();
+
+        if self.current_lexeme() != "(" {
+            return Ok(parameters)
+        }
+
+        self.next()?;
+
+        while self.current_lexeme() != ")" && self.remaining() > 0 {
+            self.eat_lexeme("@")?;
+
+            let name = self.eat_type(&TokenType::Identifier)?;
+
+            let default = if self.current_lexeme() == ":" {
+                self.next()?;
+
+                Some(self.parse_space_list()?)
+            } else {
+                None
+            };
+
+            parameters.push(Parameter { name, default });
+
+            if self.current_lexeme() != ")" {
+                self.eat_lexeme(",")?;
+            }
+        }
+
+        self.eat_lexeme(")")?;
+
+        Ok(parameters)
+    }
+
+    // `@use "tokens"`, `@use "tokens" as t` or `@use "tokens" as *`
+    fn parse_use(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let path = self.eat_type(&TokenType::Str)?;
+
+        let namespace = if self.current_lexeme() == "as" {
+            self.next()?;
+
+            if self.current_lexeme() == "*" {
+                self.next()?;
+
+                Some(String::from("*"))
+            } else {
+                Some(self.eat_type(&TokenType::Identifier)?)
+            }
         } else {
-            if self.indent % self.indent_base != 0 {
-                return Err(
-                    response!(
-                        Wrong("inconsistent indentation is not cool"),
-                        self.source.file,
-                        self.current_position()
-                    )
+            None
+        };
+
+        let pos = self.span_from(position);
+
+        if self.remaining() > 1 {
+            self.new_line()?;
+        }
+
+        Ok(
+            Statement::new(
+                StatementNode::Use(path, namespace),
+                pos
+            )
+        )
+    }
+
+    // `@import "print.css" print` or `@import url(https://example.com/a.css)`
+    fn parse_import(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let target_position = self.current_position();
+
+        let target = match self.current_type() {
+            TokenType::Str => format!("\"{}\"", self.eat()?),
+
+            TokenType::Identifier if self.current_lexeme().starts_with("url(") => self.eat()?,
+
+            // `url("a.css")`
+            TokenType::Identifier if self.current_lexeme() == "url" && self.is_adjacent() => {
+                self.next()?;
+                self.eat_lexeme("(")?;
+
+                let url = self.eat_type(&TokenType::Str)?;
+
+                self.eat_lexeme(")")?;
+
+                format!("url(\"{}\")", url)
+            },
+
+            _ => return Err(
+                Diagnostic::error(
+                    format!("expected a string or `url(…)` to import, found `{}`", self.current_lexeme()),
+                    &self.source.file,
+                    self.current_position()
                 )
+            )
+        };
+
+        let url = target.trim_start_matches("url(").trim_end_matches(')').trim_matches('"');
+
+        let plain = url.ends_with(".css")
+            || url.starts_with("http://")
+            || url.starts_with("https://")
+            || url.starts_with("//")
+            || target.starts_with("url(");
+
+        if !plain {
+            return Err(
+                Diagnostic::error(
+                    format!("`@import` is only for plain CSS, `{}` isn't a `.css` file or a url", url),
+                    &self.source.file,
+                    self.span_from(target_position)
+                ).with_help(format!("nss files are loaded with `@use \"{}\"`", url))
+            )
+        }
+
+        let media = if self.current_lexeme() != "\n" && self.remaining() > 1 {
+            self.parse_media_query_list()?
+        } else {
+            Vec::new()
+        };
+
+        let pos = self.span_from(position);
+
+        if self.remaining() > 1 {
+            self.new_line()?;
+        }
+
+        Ok(
+            Statement::new(
+                StatementNode::Import(target, media),
+                pos
+            )
+        )
+    }
+
+    fn parse_include(&mut self, position: Pos) -> Result<Statement, Diagnostic> {
+        let mut namespace = None;
+        let mut name      = self.eat_type(&TokenType::Identifier)?;
+
+        // `@include tokens.button`
+        if self.current_lexeme() == "." && self.follows_previous() {
+            self.next()?;
+
+            namespace = Some(name);
+            name      = self.eat_type(&TokenType::Identifier)?;
+        }
+
+        let arguments = self.parse_arguments()?;
+
+        let pos = self.span_from(position.clone());
+
+        // an indented block after the include is passed to `@content`
+        let content = if self.has_block((position.1).0 - 1) {
+            self.eat_lexeme("\n")?;
+
+            Some(self.parse_body()?)
+        } else {
+            if self.remaining() > 1 {
+                self.new_line()?;
+            }
+
+            None
+        };
+
+        Ok(
+            Statement::new(
+                StatementNode::Include(namespace, name, arguments, content),
+                pos
+            )
+        )
+    }
+
+    // `(1px, @color: red)`, the parentheses are optional without arguments
+    fn parse_arguments(&mut self) -> Result<Vec<Argument>, Diagnostic> {
+        let mut arguments = Vec::new();
+
+        if self.current_lexeme() != "(" || !self.follows_previous() {
+            return Ok(arguments)
+        }
+
+        let open = self.current_position();
+
+        self.next()?;
+        self.next_continued();
+
+        // arguments aren't parenthesized arithmetic, `rgb(255 0 0 / 50%)` keeps its slash
+        let depth = std::mem::replace(&mut self.depth, 0);
+
+        while self.current_lexeme() != ")" && self.remaining() > 0 {
+            let named = self.current_lexeme() == "@"
+                && self.tokens.get(self.index + 2).is_some_and(|t| t.lexeme == ":");
+
+            let name = if named {
+                self.next()?;
+
+                let name = self.eat_type(&TokenType::Identifier)?;
+
+                self.next()?;
+
+                Some(name)
+            } else {
+                None
+            };
+
+            let value = self.parse_space_list()?;
+
+            arguments.push(Argument { name, value });
+
+            self.next_continued();
+
+            if self.current_lexeme() != ")" {
+                if self.current_lexeme() != "," {
+                    return Err(self.unclosed(&open))
+                }
+
+                self.next()?;
+                self.next_continued();
+            }
+        }
+
+        self.depth = depth;
+
+        self.close(&open)?;
+
+        Ok(arguments)
+    }
+
+    pub fn parse_selector_list(&mut self) -> Result<Vec<Selector>, Diagnostic> {
+        let mut selectors = vec![self.parse_selector()?];
+
+        while self.current_lexeme() == "," && self.remaining() > 0 {
+            self.next()?;
+            self.next_newline()?;
+
+            selectors.push(self.parse_selector()?)
+        }
+
+        Ok(selectors)
+    }
+
+    pub fn parse_selector(&mut self) -> Result<Selector, Diagnostic> {
+        let mut parts = Vec::new();
+
+        loop {
+            let combinator = match self.current_lexeme().as_str() {
+                ">" => Some(Combinator::Child),
+                "+" => Some(Combinator::NextSibling),
+                "~" => Some(Combinator::SubsequentSibling),
+                _   => None,
+            };
+
+            if let Some(combinator) = combinator {
+                if let Some(SelectorPart::Combinator(_)) = parts.last() {
+                    return Err(
+                        Diagnostic::error(
+                            "two combinators in a row",
+                            &self.source.file,
+                            self.current_position()
+                        )
+                    )
+                }
+
+                self.next()?;
+
+                parts.push(SelectorPart::Combinator(combinator));
+
+                continue
+            }
+
+            if self.remaining() == 0 || [",", ")", "\n"].contains(&self.current_lexeme().as_str()) {
+                break
+            }
+
+            // whitespace between two compounds
+            if let Some(SelectorPart::Compound(_)) = parts.last() {
+                parts.push(SelectorPart::Combinator(Combinator::Descendant))
+            }
+
+            parts.push(SelectorPart::Compound(self.parse_compound_selector()?))
+        }
+
+        match parts.last() {
+            Some(SelectorPart::Compound(_)) => Ok(Selector(parts)),
+
+            _ => Err(
+                Diagnostic::error(
+                    format!("expected a selector, found {}", self.current_type()),
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        }
+    }
+
+    fn parse_compound_selector(&mut self) -> Result<Vec<SimpleSelector>, Diagnostic> {
+        let mut simples = vec![self.parse_simple_selector()?];
+
+        while self.remaining() > 0 && self.follows_previous() {
+            if [",", ")", "\n", ">", "+", "~"].contains(&self.current_lexeme().as_str()) {
+                break
+            }
+
+            simples.push(self.parse_simple_selector()?)
+        }
+
+        Ok(simples)
+    }
+
+    fn parse_simple_selector(&mut self) -> Result<SimpleSelector, Diagnostic> {
+        use self::TokenType::*;
+
+        let simple = match self.current_type() {
+            Identifier => SimpleSelector::Type(self.parse_interpolation()?),
+            Color      => SimpleSelector::Id(Interpolation::text(&self.eat()?)), // `#add` looks like a color
+
+            _ if self.starts_interpolation() => SimpleSelector::Type(self.parse_interpolation()?),
+
+            _ => match self.current_lexeme().as_str() {
+                "*" => {
+                    self.next()?;
+
+                    SimpleSelector::Universal
+                },
+
+                "&" => {
+                    self.next()?;
+
+                    let suffix = if self.follows_previous() && self.starts_name() {
+                        Some(self.parse_interpolation()?)
+                    } else {
+                        None
+                    };
+
+                    SimpleSelector::Parent(suffix)
+                },
+
+                "." => {
+                    self.next()?;
+
+                    SimpleSelector::Class(self.parse_selector_name()?)
+                },
+
+                "#" => {
+                    self.next()?;
+
+                    SimpleSelector::Id(self.parse_selector_name()?)
+                },
+
+                "[" => {
+                    self.next()?;
+
+                    SimpleSelector::Attribute(self.parse_attribute_selector()?)
+                },
+
+                ":" => {
+                    self.next()?;
+
+                    let element = self.current_lexeme() == ":" && self.follows_previous();
+
+                    if element {
+                        self.next()?
+                    }
+
+                    let name = self.eat_selector_name()?;
+
+                    let argument = if self.current_lexeme() == "(" && self.follows_previous() {
+                        self.next()?;
+
+                        let argument = if ["not", "is", "where", "has", "matches"].contains(&name.as_str()) {
+                            PseudoArgument::Selectors(self.parse_selector_list()?)
+                        } else {
+                            PseudoArgument::Raw(self.eat_raw_argument()?)
+                        };
+
+                        self.eat_lexeme(")")?;
+
+                        Some(argument)
+                    } else {
+                        None
+                    };
+
+                    if element {
+                        SimpleSelector::PseudoElement(name, argument)
+                    } else {
+                        SimpleSelector::PseudoClass(name, argument)
+                    }
+                },
+
+                c => return Err(
+                    Diagnostic::error(
+                        format!("unexpected `{}` in selector", c),
+                        &self.source.file,
+                        self.current_position()
+                    )
+                )
+            }
+        };
+
+        Ok(simple)
+    }
+
+    fn parse_attribute_selector(&mut self) -> Result<AttributeSelector, Diagnostic> {
+        let name = self.eat_selector_name()?;
+
+        let matcher = if self.current_lexeme() == "]" {
+            None
+        } else {
+            let mut operator = String::new();
+
+            if ["~", "|", "^", "$", "*"].contains(&self.current_lexeme().as_str()) {
+                operator.push_str(&self.eat()?)
+            }
+
+            operator.push_str(&self.eat_lexeme("=")?);
+
+            let value = match self.current_type() {
+                TokenType::Str => format!("\"{}\"", self.eat()?),
+                TokenType::Identifier | TokenType::Int => self.eat()?,
+
+                ref t => return Err(
+                    Diagnostic::error(
+                        format!("expected an attribute value, found {}", t),
+                        &self.source.file,
+                        self.current_position()
+                    )
+                )
+            };
+
+            Some((operator, value))
+        };
+
+        let flag = if self.current_type() == TokenType::Identifier {
+            Some(self.eat()?)
+        } else {
+            None
+        };
+
+        self.eat_lexeme("]")?;
+
+        Ok(
+            AttributeSelector {
+                name,
+                matcher,
+                flag,
+            }
+        )
+    }
+
+    // the name right after `.`, `#` or `:`, no whitespace allowed
+    fn eat_selector_name(&mut self) -> Result<String, Diagnostic> {
+        if self.current_type() == TokenType::Identifier && self.follows_previous() {
+            self.eat()
+        } else {
+            Err(
+                Diagnostic::error(
+                    format!("expected a name, found `{}`", self.current_lexeme()),
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        }
+    }
+
+    // like `eat_selector_name`, but the name may be interpolated
+    fn parse_selector_name(&mut self) -> Result<Interpolation, Diagnostic> {
+        if self.follows_previous() && self.starts_name() {
+            self.parse_interpolation()
+        } else {
+            Err(
+                Diagnostic::error(
+                    format!("expected a name, found `{}`", self.current_lexeme()),
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        }
+    }
+
+    // adjacent pieces of a name and `#{…}` interpolations, like `mt-#{@i}`
+    fn parse_interpolation(&mut self) -> Result<Interpolation, Diagnostic> {
+        use self::TokenType::*;
+
+        let mut segments = Vec::new();
+
+        loop {
+            if self.starts_interpolation() {
+                self.next()?;
+                self.next()?;
+
+                segments.push(Segment::Expression(self.parse_expression()?));
+
+                self.eat_lexeme("}")?;
+            } else if self.starts_name() {
+                segments.push(Segment::Text(self.eat()?))
+            } else if segments.is_empty() {
+                return Err(
+                    Diagnostic::error(
+                        format!("expected a name, found `{}`", self.current_lexeme()),
+                        &self.source.file,
+                        self.current_position()
+                    )
+                )
+            } else {
+                break
+            }
+
+            if self.remaining() == 0 || !self.follows_previous() || self.current_type() == EOL {
+                break
+            }
+        }
+
+        Ok(Interpolation(segments))
+    }
+
+    // e.g. `2n + 1`, as written up to the closing parenthesis
+    fn eat_raw_argument(&mut self) -> Result<String, Diagnostic> {
+        let mut raw   = String::new();
+        let mut depth = 0;
+
+        while self.remaining() > 0 && self.current_lexeme() != "\n" {
+            match self.current_lexeme().as_str() {
+                "(" => depth += 1,
+                ")" if depth == 0 => break,
+                ")" => depth -= 1,
+                _ => ()
+            }
+
+            if !raw.is_empty() && !self.follows_previous() {
+                raw.push(' ')
+            }
+
+            if self.current_type() == TokenType::Str {
+                raw.push_str(&format!("\"{}\"", self.eat()?))
+            } else {
+                raw.push_str(&self.eat()?)
+            }
+        }
+
+        Ok(raw)
+    }
+
+    pub fn parse_media_query_list(&mut self) -> Result<Vec<MediaQuery>, Diagnostic> {
+        let mut queries = vec![self.parse_media_query()?];
+
+        while self.current_lexeme() == "," && self.remaining() > 0 {
+            self.next()?;
+
+            queries.push(self.parse_media_query()?)
+        }
+
+        Ok(queries)
+    }
+
+    fn parse_media_query(&mut self) -> Result<MediaQuery, Diagnostic> {
+        let mut modifier   = None;
+        let mut media_type = None;
+        let mut features   = Vec::new();
+
+        if ["not", "only"].contains(&self.current_lexeme().as_str()) {
+            modifier = Some(self.eat()?)
+        }
+
+        if self.current_type() == TokenType::Identifier {
+            media_type = Some(self.eat()?)
+        } else if modifier.is_some() {
+            return Err(
+                Diagnostic::error(
+                    format!("expected a media type after `{}`", modifier.unwrap()),
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        }
+
+        loop {
+            if media_type.is_some() || !features.is_empty() {
+                if self.current_lexeme() == "and" {
+                    self.next()?
+                } else {
+                    break
+                }
+            }
+
+            features.push(self.parse_media_feature()?)
+        }
+
+        Ok(
+            MediaQuery {
+                modifier,
+                media_type,
+                features,
+            }
+        )
+    }
+
+    fn parse_media_feature(&mut self) -> Result<MediaFeature, Diagnostic> {
+        if self.starts_interpolation() {
+            return Ok(MediaFeature::Interpolated(self.parse_interpolation()?))
+        }
+
+        self.eat_lexeme("(")?;
+
+        let next = self.tokens.get(self.index + 1).map(|t| t.lexeme.clone()).unwrap_or_default();
+
+        let named = self.current_type() == TokenType::Identifier && [":", ")", "#"].contains(&next.as_str());
+
+        let feature = if named || self.starts_interpolation() {
+            let name = self.parse_interpolation()?;
+
+            if self.current_lexeme() == ":" {
+                self.next()?;
+
+                MediaFeature::Plain(name, self.parse_space_list()?)
+            } else {
+                MediaFeature::Boolean(name)
+            }
+        } else if self.current_type() == TokenType::Identifier && ["<", ">", "=", "<=", ">="].contains(&next.as_str()) {
+            let name       = self.eat()?;
+            let comparison = self.eat_comparison()?;
+
+            MediaFeature::Range(None, name, Some((comparison, self.parse_arithmetic()?)))
+        } else {
+            let lower = (self.parse_arithmetic()?, self.eat_comparison()?);
+            let name  = self.eat_type(&TokenType::Identifier)?;
+
+            let upper = if ["<", ">", "=", "<=", ">="].contains(&self.current_lexeme().as_str()) {
+                Some((self.eat_comparison()?, self.parse_arithmetic()?))
+            } else {
+                None
+            };
+
+            MediaFeature::Range(Some(lower), name, upper)
+        };
+
+        self.eat_lexeme(")")?;
+
+        Ok(feature)
+    }
+
+    // `<`, `>`, `=`, `<=` or `>=`
+    fn eat_comparison(&mut self) -> Result<String, Diagnostic> {
+        let mut comparison = match self.current_lexeme().as_str() {
+            c @ "<" | c @ ">" | c @ "=" | c @ "<=" | c @ ">=" => c.to_string(),
+
+            c => return Err(
+                Diagnostic::error(
+                    format!("expected a comparison, found `{}`", c),
+                    &self.source.file,
+                    self.current_position()
+                )
+            )
+        };
+
+        self.next()?;
+
+        if (comparison == "<" || comparison == ">") && self.current_lexeme() == "=" && self.follows_previous() {
+            self.next()?;
+
+            comparison.push('=')
+        }
+
+        Ok(comparison)
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expression, Diagnostic> {
+        let position = self.current_position();
+        let first    = self.parse_space_list()?;
+
+        let expression = if self.current_lexeme() == "," && self.remaining() > 0 {
+            let mut items = vec![first];
+
+            while self.current_lexeme() == "," && self.remaining() > 0 {
+                self.next()?;
+                self.next_newline()?;
+
+                items.push(self.parse_space_list()?)
+            }
+
+            Expression::new(
+                ExpressionNode::List(items, Separator::Comma),
+                self.span_from(position)
+            )
+        } else {
+            first
+        };
+
+        // `!` on its own or `!important`, variable flags are left to the assignment
+        let flag = self.tokens.get(self.index + 1).map(|t| t.lexeme.clone()).unwrap_or_default();
+
+        if self.current_lexeme() == "!" && flag != "global" && flag != "default" && self.remaining() > 0 {
+            self.next()?;
+
+            if flag == "important" && self.follows_previous() {
+                self.next()?
+            }
+
+            let position = expression.pos.clone();
+
+            return Ok(
+                Expression::new(
+                    ExpressionNode::Important(
+                        Rc::new(expression)
+                    ),
+                    self.span_from(position)
+                )
+            )
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_space_list(&mut self) -> Result<Expression, Diagnostic> {
+        let position  = self.current_position();
+        let mut items = vec![self.parse_binary(0)?];
+
+        while self.remaining() > 0 && self.starts_expression() {
+            items.push(self.parse_binary(0)?)
+        }
+
+        if items.len() == 1 {
+            Ok(items.pop().unwrap())
+        } else {
+            Ok(
+                Expression::new(
+                    ExpressionNode::List(items, Separator::Space),
+                    self.span_from(position)
+                )
+            )
+        }
+    }
+
+    // operands of comparisons, e.g. in media query ranges
+    fn parse_arithmetic(&mut self) -> Result<Expression, Diagnostic> {
+        let (_, precedence) = Operator::from_str("+").unwrap();
+
+        self.parse_binary(precedence)
+    }
+
+    // precedence climbing, `^` being the only right-associative operator
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, Diagnostic> {
+        let mut left = self.parse_atom()?;
+
+        while self.current_type() != TokenType::Str && self.remaining() > 0 {
+            let (operator, precedence) = match Operator::from_str(&self.current_lexeme()) {
+                Some(op) => op,
+                None     => break
+            };
+
+            if precedence < min_precedence {
+                break
             }
-        }
 
-        let mut accum = Vec::new();
+            // `0 -@gutter` is a list of two, like `0 -2px`
+            if operator == Operator::Sub && !self.follows_previous() && self.is_adjacent() {
+                break
+            }
 
-        while !self.is_dedent() && self.remaining() > 0 {
-            let statement = self.parse_statement()?;
+            self.next()?;
 
-            self.next_newline()?;
+            let right = if operator == Operator::Pow {
+                self.parse_binary(precedence)?
+            } else {
+                self.parse_binary(precedence + 1)?
+            };
 
-            accum.push(statement)
-        }
-        
-        self.indent = backup_indent;
+            let position = left.pos.clone();
 
-        Ok(accum)
-    }
+            // outside of parentheses `12px/1.5` and `1 / 2 / 3` are plain css slashes, not divisions
+            let node = if operator == Operator::Div && self.depth == 0 && left.is_slash_operand() && right.is_slash_operand() {
+                match left.node {
+                    ExpressionNode::List(mut items, Separator::Slash) => {
+                        items.push(right);
+
+                        ExpressionNode::List(items, Separator::Slash)
+                    },
 
-    pub fn parse_expression(&mut self) -> Result<Expression, ()> {
-        let atom = self.parse_atom()?;
+                    _ => ExpressionNode::List(vec![left, right], Separator::Slash)
+                }
+            } else {
+                ExpressionNode::Binary(
+                    Rc::new(left),
+                    operator,
+                    Rc::new(right)
+                )
+            };
 
-        if self.current_type() == TokenType::Operator {
-            Ok(atom) // later
-        } else {
-            Ok(atom)
+            left = Expression::new(node, self.span_from(position))
         }
+
+        Ok(left)
     }
 
-    pub fn parse_atom(&mut self) -> Result<Expression, ()> {
+    pub fn parse_atom(&mut self) -> Result<Expression, Diagnostic> {
         use self::TokenType::*;
 
         if self.remaining() == 0 {
@@ -213,27 +1269,168 @@ impl<'a> Parser<'a> {
             let position   = self.current_position();
 
             let expr = match token_type {
-                Int => Expression::new(
-                    ExpressionNode::Int(self.eat()?.parse::<i32>().unwrap()),
-                    position
-                ),
+                // past what fits an `i32`, like `z-index: 3000000000`
+                Int => {
+                    let lexeme = self.eat()?;
+
+                    let node = match lexeme.parse::<i32>() {
+                        Ok(n)  => ExpressionNode::Int(n),
+                        Err(_) => ExpressionNode::Float(lexeme.parse::<f64>().unwrap()),
+                    };
+
+                    Expression::new(node, position)
+                },
 
                 Float => Expression::new(
-                    ExpressionNode::Float(self.eat()?.parse::<f32>().unwrap()),
+                    ExpressionNode::Float(self.eat()?.parse::<f64>().unwrap()),
                     position
                 ),
 
-                Str => Expression::new(
-                    ExpressionNode::Str(self.eat()?),
-                    position
+                Dimension => {
+                    let lexeme = self.eat()?;
+                    let split  = lexeme.find(|c: char| c.is_alphabetic() || c == '%').unwrap();
+
+                    let (number, unit) = lexeme.split_at(split);
+
+                    Expression::new(
+                        ExpressionNode::Dimension(
+                            number.parse::<f64>().unwrap(),
+                            unit.to_string()
+                        ),
+                        position
+                    )
+                },
+
+                Color => {
+                    let hex = self.eat()?;
+
+                    if ![3, 4, 6, 8].contains(&hex.len()) {
+                        return Err(
+                            Diagnostic::error(
+                                format!(
+                                    "hex colors have 3, 4, 6 or 8 digits, `#{}` has {}",
+                                    hex,
+                                    hex.len()
+                                ),
+                                &self.source.file,
+                                position.clone()
+                            )
+                        )
+                    }
+
+                    Expression::new(
+                        ExpressionNode::Color(hex),
+                        position
+                    )
+                },
+
+                Str => self.parse_string()?,
+
+                // `tokens.@primary` or `tokens.double(2)`
+                Identifier if self.starts_member() => {
+                    let namespace = self.eat()?;
+
+                    self.next()?;
+
+                    let member_position = self.current_position();
+
+                    let member = if self.current_lexeme() == "@" {
+                        self.next()?;
+
+                        ExpressionNode::Deref(self.eat_type(&Identifier)?)
+                    } else {
+                        ExpressionNode::Identifier(self.eat()?)
+                    };
+
+                    Expression::new(
+                        ExpressionNode::Member(
+                            namespace,
+                            Rc::new(Expression::new(member, self.span_from(member_position)))
+                        ),
+                        self.span_from(position)
+                    )
+                },
+
+                Identifier if self.is_adjacent() && self.interpolation_at(self.index + 1) => Expression::new(
+                    ExpressionNode::Interpolation(self.parse_interpolation()?),
+                    self.span_from(position)
                 ),
 
+                Identifier if self.current_lexeme() == "not" && self.is_followed_by_operand() => {
+                    self.next()?;
+
+                    let operand = self.parse_atom()?;
+
+                    return Ok(
+                        Expression::new(
+                            ExpressionNode::Not(
+                                Rc::new(operand)
+                            ),
+                            self.span_from(position)
+                        )
+                    )
+                },
+
                 Identifier => Expression::new(
                     ExpressionNode::Identifier(self.eat()?),
                     position
                 ),
 
+                Operator if self.current_lexeme() == "-" => {
+                    self.next()?;
+
+                    let operand = self.parse_atom()?;
+
+                    return Ok(
+                        Expression::new(
+                            ExpressionNode::Neg(
+                                Rc::new(operand)
+                            ),
+                            self.span_from(position)
+                        )
+                    )
+                },
+
                 Symbol => match self.current_lexeme().as_str() {
+                    "(" => {
+                        self.next()?;
+                        self.next_continued();
+
+                        self.depth += 1;
+
+                        let expression = if self.starts_map() {
+                            self.parse_map(position.clone())?
+                        } else {
+                            self.parse_expression()?
+                        };
+
+                        self.depth -= 1;
+
+                        self.close(&position)?;
+
+                        return Ok(expression)
+                    },
+
+                    "#" if self.starts_interpolation() => Expression::new(
+                        ExpressionNode::Interpolation(self.parse_interpolation()?),
+                        self.span_from(position)
+                    ),
+
+                    "#" if self.is_adjacent() => {
+                        self.next()?;
+
+                        return Err(
+                            Diagnostic::error(
+                                format!(
+                                    "`#{}` is not a hex color, only 0-9 and a-f are allowed",
+                                    self.current_lexeme()
+                                ),
+                                &self.source.file,
+                                self.span_from(position)
+                            )
+                        )
+                    },
+
                     "@" => {
                         self.next()?;
 
@@ -241,16 +1438,14 @@ impl<'a> Parser<'a> {
                             ExpressionNode::Deref(
                                 self.eat_type(&Identifier)?
                             ),
-                            self.current_position()
+                            self.span_from(position)
                         )
                     },
 
                     c => return Err(
-                        response!(
-                            Wrong(
-                                format!("unexpected symbol `{}`", c),
-                            ),
-                            self.source.file,
+                        Diagnostic::error(
+                            format!("unexpected symbol `{}`", c),
+                            &self.source.file,
                             self.current_position()
                         )
                     )
@@ -258,11 +1453,9 @@ impl<'a> Parser<'a> {
 
                 ref tt => {
                     return Err(
-                        response!(
-                            Wrong(
-                                format!("unexpected token `{}`", tt),
-                            ),
-                            self.source.file,
+                        Diagnostic::error(
+                            format!("unexpected token `{}`", tt),
+                            &self.source.file,
                             self.current_position()
                         )
                     )
@@ -277,77 +1470,182 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_postfix(&mut self, expression: Expression) -> Result<Expression, ()> {
-        let backup_index = self.index;
-
-        match self.current_lexeme().as_str() {
-            "(" => {
-                self.next()?;
-                self.next_newline()?;
-                
-                let mut args = Vec::new();
-
-                while !["\n", ")"].contains(&self.current_lexeme().as_str()) {
-                    args.push(self.parse_expression()?);
+    // `"icon-#{@name}.svg"`, the interpolations are lexed on the spot
+    fn parse_string(&mut self) -> Result<Expression, Diagnostic> {
+        let position = self.current_position();
+        let token    = self.current();
+        let string   = self.eat()?;
 
-                    if !["\n", ")"].contains(&self.current_lexeme().as_str()) && self.remaining() > 0 {
-                        self.eat_lexeme(",")?;
-                        self.next_newline()?;
-                    }
-                }
+        if !string.contains("#{") {
+            return Ok(Expression::new(ExpressionNode::Str(string), position))
+        }
 
-                self.next_newline()?;
-                self.eat_lexeme(")")?;
+        let chars = string.chars().collect::<Vec<char>>();
 
-                let position = expression.pos.clone();
+        let mut segments = Vec::new();
+        let mut text     = String::new();
+        let mut i        = 0;
 
-                let expr = Expression::new(
-                    ExpressionNode::Call(
-                        Rc::new(expression),
-                        args,
-                    ),
-                    self.span_from(position),
-                );
+        while i < chars.len() {
+            if chars[i] != '#' || chars.get(i + 1) != Some(&'{') {
+                text.push(chars[i]);
+                i += 1;
 
-                return self.parse_postfix(expr)
+                continue
             }
 
-            "!" => {
-                self.next()?;
+            let start     = i + 2;
+            let mut end   = start;
+            let mut depth = 0;
 
-                let position = expression.pos.clone();
+            while end < chars.len() {
+                match chars[end] {
+                    '{' => depth += 1,
+                    '}' if depth == 0 => break,
+                    '}' => depth -= 1,
+                    _ => ()
+                }
 
-                return Ok(
-                    Expression::new(
-                        ExpressionNode::Important(
-                            Rc::new(expression)
-                        ),
-                        self.span_from(position)
+                end += 1
+            }
+
+            if end == chars.len() {
+                return Err(
+                    Diagnostic::error(
+                        "unterminated `#{` in string",
+                        &self.source.file,
+                        position.clone()
                     )
                 )
             }
 
-            _ => ()
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)))
+            }
+
+            let inner = chars[start .. end].iter().collect::<String>();
+
+            // the quote comes before the first character
+            segments.push(Segment::Expression(self.parse_inline(&inner, &token, token.slice.0 + start)?));
+
+            i = end + 1
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text))
+        }
+
+        Ok(
+            Expression::new(
+                ExpressionNode::InterpolatedStr(Interpolation(segments)),
+                position
+            )
+        )
+    }
+
+    // parses the text of an interpolation inside of `token`, starting at column `offset`
+    fn parse_inline(&self, text: &str, token: &Token, offset: usize) -> Result<Expression, Diagnostic> {
+        let mut tokens = Vec::new();
+
+        for inner in Lexer::default(text.chars().collect(), self.source) {
+            let mut inner = inner?;
+
+            inner.line     = token.line.clone();
+            inner.slice.0 += offset;
+            inner.slice.1 += offset;
+
+            tokens.push(inner)
+        }
+
+        if tokens.is_empty() {
+            return Err(
+                Diagnostic::error(
+                    "empty interpolation",
+                    &self.source.file,
+                    Pos(token.line.clone(), (offset - 1, offset))
+                )
+            )
+        }
+
+        let mut parser = Parser::new(tokens, self.source);
+        let expression = parser.parse_expression()?;
+
+        if parser.remaining() > 0 {
+            return Err(
+                Diagnostic::error(
+                    format!("unexpected `{}` in interpolation", parser.current_lexeme()),
+                    &self.source.file,
+                    parser.current_position()
+                )
+            )
         }
 
         Ok(expression)
     }
 
+    // `(key: value, …)`, the opening parenthesis already eaten
+    fn parse_map(&mut self, position: Pos) -> Result<Expression, Diagnostic> {
+        let mut pairs = Vec::new();
+
+        loop {
+            let key = self.parse_space_list()?;
+
+            self.eat_lexeme(":")?;
+
+            pairs.push((key, self.parse_space_list()?));
+
+            if self.current_lexeme() != "," {
+                break
+            }
+
+            self.next()?;
+            self.next_continued();
+
+            // trailing comma
+            if self.current_lexeme() == ")" {
+                break
+            }
+        }
+
+        Ok(
+            Expression::new(
+                ExpressionNode::Map(pairs),
+                self.span_from(position)
+            )
+        )
+    }
+
+    fn parse_postfix(&mut self, expression: Expression) -> Result<Expression, Diagnostic> {
+        if self.current_lexeme() == "(" && self.follows_previous() {
+            let args = self.parse_arguments()?;
 
+            let position = expression.pos.clone();
 
-    fn new_line(&mut self) -> Result<(), ()> {
+            let expr = Expression::new(
+                ExpressionNode::Call(
+                    Rc::new(expression),
+                    args,
+                ),
+                self.span_from(position),
+            );
+
+            return self.parse_postfix(expr)
+        }
+
+        Ok(expression)
+    }
+
+    fn new_line(&mut self) -> Result<(), Diagnostic> {
         if self.remaining() > 0 {
             match self.current_lexeme().as_str() {
                 "\n" => self.next(),
                 _    => Err(
-                    response!(
-                        Wrong(
-                            format!(
-                                "expected new line, found: `{}`",
-                                self.current_lexeme()
-                            )
+                    Diagnostic::error(
+                        format!(
+                            "expected new line, found: `{}`",
+                            self.current_lexeme()
                         ),
-                        self.source.file,
+                        &self.source.file,
                         self.current_position()
                     )
                 )
@@ -378,23 +1676,23 @@ impl<'a> Parser<'a> {
         self.tokens.len().saturating_sub(self.index)
     }
 
-    fn next(&mut self) -> Result<(), ()> {
+    fn next(&mut self) -> Result<(), Diagnostic> {
         if self.index <= self.tokens.len() {
             self.index += 1;
 
             Ok(())
         } else {
             Err(
-                response!(
-                    Wrong("nexting too far"),
-                    self.source.file,
+                Diagnostic::error(
+                    "nexting too far",
+                    &self.source.file,
                     self.current_position()
                 )
             )
         }
     }
 
-    fn next_newline(&mut self) -> Result<(), ()> {
+    fn next_newline(&mut self) -> Result<(), Diagnostic> {
         while self.current_lexeme() == "\n" && self.remaining() > 0 {
             self.next()?
         }
@@ -402,6 +1700,44 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    // inside of parentheses a value goes on over lines indented deeper than its statement,
+    // up to a line closing the parentheses
+    fn next_continued(&mut self) {
+        let mut index = self.index;
+
+        while self.tokens.get(index).is_some_and(|token| token.lexeme == "\n") {
+            index += 1
+        }
+
+        if let Some(token) = self.tokens.get(index) {
+            if token.slice.0 - 1 > self.statement_indent || token.lexeme == ")" {
+                self.index = index
+            }
+        }
+    }
+
+    fn close(&mut self, open: &Pos) -> Result<(), Diagnostic> {
+        self.next_continued();
+
+        if self.current_lexeme() == ")" && self.remaining() > 0 {
+            self.next()
+        } else {
+            Err(self.unclosed(open))
+        }
+    }
+
+    // reported where the parentheses open, what comes after is likely fine
+    fn unclosed(&self, open: &Pos) -> Diagnostic {
+        let found = match self.current_lexeme().as_str() {
+            "\n" => String::from("the end of the line"),
+            _ if self.remaining() == 0 => String::from("the end of the file"),
+            lexeme => format!("`{}`", lexeme),
+        };
+
+        Diagnostic::error("`(` is never closed", &self.source.file, open.clone())
+            .with_note(format!("expected `)` but found {}", found))
+    }
+
     fn get_indent(&self) -> usize {
         self.current().slice.0 - 1
     }
@@ -410,11 +1746,134 @@ impl<'a> Parser<'a> {
         self.get_indent() < self.indent && self.current_lexeme() != "\n"
     }
 
+    fn is_followed_by_operand(&self) -> bool {
+        match self.tokens.get(self.index + 1) {
+            Some(next) => next.line.0 == self.current().line.0 && ![",", ")", "\n"].contains(&next.lexeme.as_str()),
+            None       => false
+        }
+    }
+
+    fn starts_interpolation(&self) -> bool {
+        self.interpolation_at(self.index)
+    }
+
+    // `#{` at the given token
+    fn interpolation_at(&self, index: usize) -> bool {
+        match (self.tokens.get(index), self.tokens.get(index + 1)) {
+            (Some(hash), Some(brace)) => {
+                hash.lexeme == "#" && brace.lexeme == "{" && hash.line.0 == brace.line.0 && brace.slice.0 == hash.slice.1 + 1
+            },
+
+            _ => false
+        }
+    }
+
+    // a piece of a name that isn't interpolated
+    fn starts_member(&self) -> bool {
+        let dot    = self.tokens.get(self.index + 1);
+        let member = self.tokens.get(self.index + 2);
+
+        match (dot, member) {
+            (Some(dot), Some(member)) => {
+                self.is_adjacent()
+                    && dot.lexeme == "."
+                    && member.line.0 == dot.line.0
+                    && member.slice.0 == dot.slice.1 + 1
+                    && (member.lexeme == "@" || member.token_type == TokenType::Identifier)
+            },
+
+            _ => false
+        }
+    }
+
+    fn starts_name(&self) -> bool {
+        use self::TokenType::*;
+
+        match self.current_type() {
+            Identifier | Int | Dimension => true,
+            Operator => self.current_lexeme() == "-",
+            _ => self.starts_interpolation(),
+        }
+    }
+
+    // a map key is a single value followed by `:`
+    fn starts_map(&self) -> bool {
+        matches!(self.tokens.get(self.index + 1), Some(next) if next.lexeme == ":")
+    }
+
+    fn starts_expression(&self) -> bool {
+        use self::TokenType::*;
+
+        match self.current_type() {
+            Int | Float | Dimension | Str | Identifier | Color => true,
+            Symbol => ["@", "(", "#"].contains(&self.current_lexeme().as_str()),
+            Operator => self.current_lexeme() == "-" && !self.follows_previous() && self.is_adjacent(),
+            _ => false,
+        }
+    }
+
+    // whether the current token directly follows the previous one, without whitespace
+    fn follows_previous(&self) -> bool {
+        match self.tokens.get(self.index.wrapping_sub(1)) {
+            Some(previous) => {
+                let current = self.current();
+
+                previous.line.0 == current.line.0 && current.slice.0 == previous.slice.1 + 1
+            },
+
+            None => false
+        }
+    }
+
+    // a rule header is followed by a more indented line, a declaration isn't
+    fn is_rule_header(&self) -> bool {
+        self.has_block(self.get_indent())
+    }
+
+    // whether the line after the current one is indented deeper than `indent`
+    fn has_block(&self, indent: usize) -> bool {
+        let mut index = self.index;
+
+        loop {
+            while index < self.tokens.len() && self.tokens[index].token_type != TokenType::EOL {
+                index += 1
+            }
+
+            let continued = index > 0 && self.tokens[index - 1].lexeme == ",";
+
+            while index < self.tokens.len() && self.tokens[index].token_type == TokenType::EOL {
+                index += 1
+            }
+
+            // lists may continue on the next line after a comma
+            if !continued || index >= self.tokens.len() {
+                break
+            }
+        }
+
+        match self.tokens.get(index) {
+            Some(token) => token.slice.0 - 1 > indent,
+            None        => false
+        }
+    }
+
+    // whether the next token follows the current one without whitespace
+    fn is_adjacent(&self) -> bool {
+        match self.tokens.get(self.index + 1) {
+            Some(next) => {
+                let current = self.current();
+
+                next.line.0 == current.line.0 && next.slice.0 == current.slice.1 + 1
+            },
+
+            None => false
+        }
+    }
+
     fn current(&self) -> Token {
-        if self.index > self.tokens.len() - 1 {
-            self.tokens[self.tokens.len() - 1].clone()
-        } else {
-            self.tokens[self.index].clone()
+        match self.tokens.get(self.index).or_else(|| self.tokens.last()) {
+            Some(token) => token.clone(),
+            None        => Token::new(TokenType::EOF, (1, String::new()), (1, 1), ""),
         }
     }
 
@@ -432,7 +1891,7 @@ impl<'a> Parser<'a> {
         Pos(current.line.clone(), current.slice)
     }
 
-    fn eat(&mut self) -> Result<String, ()> {
+    fn eat(&mut self) -> Result<String, Diagnostic> {
         let lexeme = self.current().lexeme;
 
         self.next()?;
@@ -440,60 +1899,41 @@ impl<'a> Parser<'a> {
         Ok(lexeme)
     }
 
-    fn eat_lexeme(&mut self, lexeme: &str) -> Result<String, ()> {
+    fn eat_lexeme(&mut self, lexeme: &str) -> Result<String, Diagnostic> {
         if self.current_lexeme() == lexeme {
             let lexeme = self.current().lexeme;
             self.next()?;
 
             Ok(lexeme)
         } else {
-            Err(response!(
-                Wrong(format!(
+            Err(Diagnostic::error(
+                format!(
                     "expected `{}` but found `{}`",
                     lexeme,
                     self.current_lexeme()
-                )),
-                self.source.file,
+                ),
+                &self.source.file,
                 self.current_position()
             ))
         }
     }
 
-    fn eat_type(&mut self, token_type: &TokenType) -> Result<String, ()> {
+    fn eat_type(&mut self, token_type: &TokenType) -> Result<String, Diagnostic> {
         if self.current_type() == *token_type {
             let lexeme = self.current().lexeme.clone();
             self.next()?;
 
             Ok(lexeme)
         } else {
-            Err(response!(
-                Wrong(format!(
+            Err(Diagnostic::error(
+                format!(
                     "expected `{}` but found `{}`",
                     token_type,
                     self.current_type()
-                )),
-                self.source.file,
+                ),
+                &self.source.file,
                 self.current_position()
             ))
         }
     }
-
-    fn expect_lexeme(&self, lexeme: &str) -> Result<(), ()> {
-        if self.current_lexeme() == lexeme {
-            Ok(())
-        } else {
-            Err(
-                response!(
-                    Wrong(
-                        format!(
-                            "expected `{}` but found `{}`",
-                            lexeme,
-                            self.current_lexeme()
-                        )
-                    ),
-                    self.source.file
-                )
-            )
-        }
-    }
-}
\ No newline at end of file
+}
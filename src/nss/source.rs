@@ -5,12 +5,22 @@ use std::fmt;
 
 use colored::Colorize;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FilePath(pub String);
 
+impl FilePath {
+    /// The header of a diagnostic, `color` for a terminal.
+    pub fn render(&self, color: bool) -> String {
+        let arrow = format!("{:>8}", "==>");
+        let arrow = if color { arrow.blue().bold().to_string() } else { arrow };
+
+        format!("\n{} {}", arrow, self.0)
+    }
+}
+
 impl fmt::Display for FilePath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "\n{:>8} {}", "==>".blue().bold(), self.0)
+        write!(f, "{}", self.render(false))
     }
 }
 
@@ -18,6 +28,7 @@ impl fmt::Display for FilePath {
 pub struct Source {
     pub file: FilePath,
     pub lines: Vec<String>,
+    pub content: String, // as it was read, line endings and all
 }
 
 impl Source {
@@ -27,16 +38,22 @@ impl Source {
 
         source.read_to_string(&mut content).unwrap();
 
-        Self {
-            file: FilePath(path),
-            lines: content.lines().map(|x| x.to_string()).collect()
-        }
+        Self::from_content(&path, &content)
     }
 
     pub fn from(path: &str, lines: Vec<String>) -> Self {
         Self {
             file: FilePath(path.into()),
+            content: lines.join("\n"),
             lines,
         }
     }
-}
\ No newline at end of file
+
+    pub fn from_content(path: &str, content: &str) -> Self {
+        Self {
+            file: FilePath(path.into()),
+            lines: content.lines().map(|x| x.to_string()).collect(),
+            content: content.to_string(),
+        }
+    }
+}